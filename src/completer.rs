@@ -30,20 +30,18 @@ impl Completer for AeroCompleter {
             if let Ok(paths) = std::env::var("PATH") {
                 for path in std::env::split_paths(&paths) {
                     if let Ok(entries) = std::fs::read_dir(path) {
-                        for entry in entries {
-                            if let Ok(entry) = entry {
-                                let name = entry.file_name().to_string_lossy().to_string();
-                                if name.starts_with(prefix) {
-                                    suggestions.push(reedline::Suggestion {
-                                        value: name,
-                                        description: None,
-                                        style: None,
-                                        extra: None,
-                                        span: reedline::Span { start, end: pos },
-                                        append_whitespace: true,
-                                        // Removed match_indices: unavailable in older reedline 0.38
-                                    });
-                                }
+                        for entry in entries.flatten() {
+                            let name = entry.file_name().to_string_lossy().to_string();
+                            if name.starts_with(prefix) {
+                                suggestions.push(reedline::Suggestion {
+                                    value: name,
+                                    description: None,
+                                    style: None,
+                                    extra: None,
+                                    span: reedline::Span { start, end: pos },
+                                    append_whitespace: true,
+                                    // Removed match_indices: unavailable in older reedline 0.38
+                                });
                             }
                         }
                     }
@@ -77,23 +75,21 @@ impl Completer for AeroCompleter {
             let search_dir = if dir.is_empty() { "." } else { dir };
 
             if let Ok(entries) = std::fs::read_dir(search_dir) {
-                for entry in entries {
-                    if let Ok(entry) = entry {
-                        let name = entry.file_name().to_string_lossy().to_string();
-                        if name.starts_with(file_part) {
-                            let mut value = format!("{}{}", dir, name);
-                            if entry.path().is_dir() {
-                                value.push('/');
-                            }
-                            suggestions.push(reedline::Suggestion {
-                                value,
-                                description: None,
-                                style: None,
-                                extra: None,
-                                span: reedline::Span { start, end: pos },
-                                append_whitespace: false,
-                            });
+                for entry in entries.flatten() {
+                    let name = entry.file_name().to_string_lossy().to_string();
+                    if name.starts_with(file_part) {
+                        let mut value = format!("{}{}", dir, name);
+                        if entry.path().is_dir() {
+                            value.push('/');
                         }
+                        suggestions.push(reedline::Suggestion {
+                            value,
+                            description: None,
+                            style: None,
+                            extra: None,
+                            span: reedline::Span { start, end: pos },
+                            append_whitespace: false,
+                        });
                     }
                 }
             }
@@ -1,25 +1,61 @@
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use std::fs;
 use std::path::PathBuf;
 use std::io::Write;
 use std::env;
 use std::collections::HashMap;
 
+use toml_edit::{value, DocumentMut, Item, Table};
+
+/// Missing sections and keys take their defaults, so a config written
+/// before a key existed still loads.
 #[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
 pub struct RootConfig {
+    /// Layout version of the file, 0 when it predates versioning. See
+    /// `config_migrate`.
+    #[serde(default)]
+    pub version: u32,
     pub config: ConfigSection,
     pub theme: ThemeSection,
-    #[serde(default)]
+    #[serde(deserialize_with = "colors_over_defaults")]
     pub colors: HashMap<String, String>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
 pub struct ConfigSection {
     pub username: String,
     pub editor: String,
+    pub duration_threshold_ms: u64,
+    pub notify_long_commands: bool,
+    pub segment_timeout_ms: u64,
+    pub theme: String,
+    pub export_ls_colors: bool,
+    pub theme_light: String,
+    pub theme_dark: String,
+    pub icons: bool,
+}
+
+impl Default for ConfigSection {
+    fn default() -> Self {
+        Self {
+            username: env::var("USER").unwrap_or_else(|_| "user".to_string()),
+            editor: "nano".to_string(),
+            duration_threshold_ms: 2000,
+            notify_long_commands: false,
+            segment_timeout_ms: 50,
+            theme: String::new(),
+            export_ls_colors: true,
+            theme_light: "solarized-light".to_string(),
+            theme_dark: "aero".to_string(),
+            icons: false,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
 pub struct ThemeSection {
     pub prompt_template: String,
     pub autocomplete: String,
@@ -30,63 +66,116 @@ pub struct ThemeSection {
     pub body: String,
     pub active: String,
     pub disable: String,
-    #[serde(default)]
+    pub git: GitColors,
+    #[serde(deserialize_with = "files_over_defaults")]
     pub files: HashMap<String, String>,
+    pub icons: HashMap<String, String>,
 }
 
-impl Default for RootConfig {
+impl Default for ThemeSection {
     fn default() -> Self {
-        let username = std::env::var("USER").unwrap_or_else(|_| "user".to_string());
-
-        let mut colors = HashMap::new();
-        colors.insert("pink".to_string(), "#FFC0CB".to_string());
-        colors.insert("white".to_string(), "#FFFFFF".to_string());
-        colors.insert("purple".to_string(), "#800080".to_string());
-        colors.insert("teal".to_string(), "#008080".to_string());
-        colors.insert("lightpink".to_string(), "#FFB6C1".to_string());
-        colors.insert("lime".to_string(), "#00FF00".to_string());
-        colors.insert("orange".to_string(), "#FFA500".to_string());
-        colors.insert("green".to_string(), "#32CD32".to_string());
-        colors.insert("red".to_string(), "#FF0000".to_string());
-        colors.insert("grey".to_string(), "#808080".to_string());
-        colors.insert("blue".to_string(), "#0000FF".to_string());
-        colors.insert("yellow".to_string(), "#FFFF00".to_string());
-
-        let mut files = HashMap::new();
-        files.insert("directory".to_string(), "blue".to_string());
-        files.insert("executable".to_string(), "orange".to_string());
-        files.insert("python".to_string(), "teal".to_string());
-        files.insert("shellscript".to_string(), "lime".to_string());
-        files.insert("rust".to_string(), "red".to_string());
-        files.insert("javascript".to_string(), "yellow".to_string());
-        files.insert("toml".to_string(), "pink".to_string());
-        files.insert("json".to_string(), "pink".to_string());
-        files.insert("default".to_string(), "white".to_string());
-        files.insert("file.zip".to_string(), "pink".to_string());
-        files.insert("file.iso".to_string(), "pink".to_string());
+        Self {
+            prompt_template: "!teal!aeroshell@!lightpink!%username%!white!<>!purple!%directory%!green!:!reset! ".to_string(),
+            autocomplete: "grey".to_string(),
+            typing: "lightpink".to_string(),
+            typingtext: "white".to_string(),
+            header: "pink".to_string(),
+            subheader: "purple".to_string(),
+            body: "white".to_string(),
+            active: "green".to_string(),
+            disable: "red".to_string(),
+            git: GitColors::default(),
+            files: default_files(),
+            icons: HashMap::new(),
+        }
+    }
+}
 
+/// Colors for the git status column in `ls`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct GitColors {
+    pub modified: String,
+    pub staged: String,
+    pub untracked: String,
+    pub ignored: String,
+    pub conflicted: String,
+}
+
+impl Default for GitColors {
+    fn default() -> Self {
         Self {
-            config: ConfigSection {
-                username,
-                editor: "nano".to_string(),
-            },
-            theme: ThemeSection {
-                prompt_template: "!teal!aeroshell@!lightpink!%username%!white!<>!purple!%directory%!green!:!reset! ".to_string(),
-                autocomplete: "grey".to_string(),
-                typing: "lightpink".to_string(),
-                typingtext: "white".to_string(),
-                header: "pink".to_string(),
-                subheader: "purple".to_string(),
-                body: "white".to_string(),
-                active: "green".to_string(),
-                disable: "red".to_string(),
-                files,
-            },
-            colors,
+            modified: "yellow".to_string(),
+            staged: "green".to_string(),
+            untracked: "red".to_string(),
+            ignored: "grey".to_string(),
+            conflicted: "bold,magenta".to_string(),
         }
     }
 }
 
+impl Default for RootConfig {
+    fn default() -> Self {
+        Self {
+            version: crate::config_migrate::CONFIG_VERSION,
+            config: ConfigSection::default(),
+            theme: ThemeSection::default(),
+            colors: default_colors(),
+        }
+    }
+}
+
+fn default_colors() -> HashMap<String, String> {
+    let mut colors = HashMap::new();
+    colors.insert("pink".to_string(), "#FFC0CB".to_string());
+    colors.insert("white".to_string(), "#FFFFFF".to_string());
+    colors.insert("purple".to_string(), "#800080".to_string());
+    colors.insert("teal".to_string(), "#008080".to_string());
+    colors.insert("lightpink".to_string(), "#FFB6C1".to_string());
+    colors.insert("lime".to_string(), "#00FF00".to_string());
+    colors.insert("orange".to_string(), "#FFA500".to_string());
+    colors.insert("green".to_string(), "#32CD32".to_string());
+    colors.insert("red".to_string(), "#FF0000".to_string());
+    colors.insert("grey".to_string(), "#808080".to_string());
+    colors.insert("blue".to_string(), "#0000FF".to_string());
+    colors.insert("yellow".to_string(), "#FFFF00".to_string());
+    colors
+}
+
+fn default_files() -> HashMap<String, String> {
+    let mut files = HashMap::new();
+    files.insert("directory".to_string(), "blue".to_string());
+    files.insert("executable".to_string(), "orange".to_string());
+    files.insert("python".to_string(), "teal".to_string());
+    files.insert("shellscript".to_string(), "lime".to_string());
+    files.insert("rust".to_string(), "red".to_string());
+    files.insert("javascript".to_string(), "yellow".to_string());
+    files.insert("toml".to_string(), "pink".to_string());
+    files.insert("json".to_string(), "pink".to_string());
+    files.insert("default".to_string(), "white".to_string());
+    files.insert("file.zip".to_string(), "pink".to_string());
+    files.insert("file.iso".to_string(), "pink".to_string());
+    files.insert("symlink".to_string(), "teal".to_string());
+    files.insert("broken_symlink".to_string(), "red".to_string());
+    files.insert("hidden".to_string(), "grey".to_string());
+    files
+}
+
+// A partial `[colors]` or `[theme.files]` adds to and overrides the
+// defaults rather than replacing them, so names the default theme uses
+// keep resolving.
+fn colors_over_defaults<'de, D: Deserializer<'de>>(deserializer: D) -> Result<HashMap<String, String>, D::Error> {
+    let mut colors = default_colors();
+    colors.extend(HashMap::<String, String>::deserialize(deserializer)?);
+    Ok(colors)
+}
+
+fn files_over_defaults<'de, D: Deserializer<'de>>(deserializer: D) -> Result<HashMap<String, String>, D::Error> {
+    let mut files = default_files();
+    files.extend(HashMap::<String, String>::deserialize(deserializer)?);
+    Ok(files)
+}
+
 pub fn get_app_root() -> PathBuf {
     if let Ok(home) = env::var("HOME") {
         return PathBuf::from(home).join("aeroshell");
@@ -98,7 +187,20 @@ pub fn get_config_path() -> PathBuf {
     get_app_root().join("config/config.toml")
 }
 
+pub fn get_themes_dir() -> PathBuf {
+    get_app_root().join("themes")
+}
+
+/// The config with the theme selected by `[config] theme` applied on top.
 pub fn load_config() -> RootConfig {
+    let mut config = load_user_config();
+    crate::theme::apply_selected_theme(&mut config);
+    crate::config_check::warn(&config);
+    config
+}
+
+/// The config exactly as written in config.toml, without applying a theme.
+pub fn load_user_config() -> RootConfig {
     let path = get_config_path();
 
     if !path.exists() {
@@ -109,13 +211,27 @@ pub fn load_config() -> RootConfig {
         return default_config;
     }
 
+    match crate::config_migrate::migrate_file(&path) {
+        Ok(Some(migrated)) => eprintln!(
+            "Updated config file from version {} to {} (old file kept as {}).",
+            migrated.from,
+            crate::config_migrate::CONFIG_VERSION,
+            migrated.backup.display()
+        ),
+        Ok(None) => {}
+        Err(e) => eprintln!("Warning: Failed to update config file: {}", e),
+    }
+
     if let Ok(content) = fs::read_to_string(&path) {
         match toml::from_str(&content) {
             Ok(config) => return config,
             Err(e) => {
-                eprintln!("\x1B[31mError parsing config file ({:?}):\x1B[0m", path);
-                eprintln!("{}", e);
+                let defaults = RootConfig::default();
+                let err_c = crate::theme::resolve(&defaults.theme.disable, &defaults).prefix();
+                let problem = crate::config_check::parse_error(&content, &e);
+                eprintln!("{}Error parsing config file:{} {}", err_c, crate::theme::reset(), problem.display(&path));
                 eprintln!("Using default configuration.");
+                return defaults;
             }
         }
     }
@@ -123,6 +239,31 @@ pub fn load_config() -> RootConfig {
     RootConfig::default()
 }
 
+/// Sets `[config] <key>` in config.toml and leaves the rest of the file,
+/// comments included, as the user wrote it. A file that doesn't load is
+/// left alone so a typo in it isn't lost.
+pub fn set_config_value(key: &str, new: &str) -> Result<(), String> {
+    let path = get_config_path();
+    let content = fs::read_to_string(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let updated = with_config_value(&content, key, new).map_err(|e| format!("{}: {}", path.display(), e))?;
+    fs::write(&path, updated).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+fn with_config_value(content: &str, key: &str, new: &str) -> Result<String, String> {
+    toml::from_str::<RootConfig>(content).map_err(|e| e.message().to_string())?;
+    let mut doc: DocumentMut = content.parse().map_err(|e: toml_edit::TomlError| e.message().to_string())?;
+    let root = doc.as_table_mut();
+    if !root.contains_key("config") {
+        root.insert("config", Item::Table(Table::new()));
+    }
+    let table = root
+        .get_mut("config")
+        .and_then(Item::as_table_mut)
+        .ok_or("[config] is not a table")?;
+    table.insert(key, value(new));
+    Ok(doc.to_string())
+}
+
 pub fn save_config(config: &RootConfig) -> std::io::Result<()> {
     let path = get_config_path();
     if let Some(parent) = path.parent() {
@@ -139,12 +280,61 @@ pub fn save_config(config: &RootConfig) -> std::io::Result<()> {
          #\n\
          # Available built-in colors (ANSI):\n\
          #   black, red, green, yellow, blue, magenta, cyan, white, grey\n\
+         #   bright_black .. bright_white\n\
          #\n\
          # Available custom colors (Hex):\n\
          #   lightpink, pink, purple, white (hex), orange, teal, lime\n\
          #\n\
          # Available styles:\n\
-         #   bold, italic, underline, reset\n\
+         #   bold, dim, italic, underline, reset\n\
+         #\n\
+         # Color specs:\n\
+         #   Anywhere a color is expected (prompt tags, [theme], [theme.files],\n\
+         #   [colors]) you can combine words separated by commas or spaces:\n\
+         #     \"bold,pink\"   \"fg:#F80 bg:234\"   \"dim italic rgb(128, 128, 128)\"\n\
+         #   Colors are names, #RRGGBB, #RGB, rgb(r, g, b) or 0-255 (256-color).\n\
+         #\n\
+         # Write !! for a literal exclamation mark in the prompt.\n\
+         #\n\
+         # Themes:\n\
+         #   Set theme = \"name\" in [config] to use a theme file instead of the\n\
+         #   [theme] section below. Themes live in ~/aeroshell/themes/<name>.toml\n\
+         #   (same [theme]/[colors] layout); aero, nord, dracula, solarized-light\n\
+         #   and mono are built in. Try them with 'aero theme list|preview|set'.\n\
+         #   The theme's [colors] are added to yours, overriding same names.\n\
+         #   theme = \"auto\" picks theme_light or theme_dark by asking the\n\
+         #   terminal for its background color (or reading $COLORFGBG).\n\
+         #   'aero theme export --format=kitty' (or alacritty, wezterm,\n\
+         #   ls_colors, toml) turns a theme into a terminal color scheme.\n\
+         #\n\
+         # LS_COLORS:\n\
+         #   With export_ls_colors = true in [config], LS_COLORS is generated\n\
+         #   from [theme.files] so GNU ls, fd and eza match the built-in ls.\n\
+         #   Globs other than \"*suffix\" are left out; GNU ls can't read them.\n\
+         #\n\
+         # Prompt variables:\n\
+         #   %username%, %hostname%, %directory%, %time%\n\
+         #   %user%           - real login user (%username% comes from [config])\n\
+         #   %root_indicator% - # when running as root, $ otherwise\n\
+         #   %shlvl%          - shell nesting level ($SHLVL)\n\
+         #   %duration% - runtime of the last command, shown only when it took\n\
+         #                longer than [config] duration_threshold_ms\n\
+         #   %git_branch% - current branch, with * when there are changes\n\
+         #   %rust_toolchain% - channel from rust-toolchain(.toml)\n\
+         #   %node_version%   - version pinned in .nvmrc / .node-version\n\
+         #   %python_venv%    - name of the active $VIRTUAL_ENV\n\
+         #   %nix_shell%      - set inside nix-shell / nix develop\n\
+         #   %container%      - docker, podman, ... when running in a container\n\
+         #   %ssh%            - \"ssh\" when connected over SSH\n\
+         #   Context variables are empty when they don't apply.\n\
+         #\n\
+         # Slow variables like %git_branch% are computed in the background. The\n\
+         # prompt waits up to [config] segment_timeout_ms for them, then shows\n\
+         # the last known value (or …) until the next keystroke or prompt.\n\
+         #\n\
+         # Long commands:\n\
+         #   Set notify_long_commands = true in [config] to get a terminal\n\
+         #   notification (OSC 9 / OSC 777) when a command passes the threshold.\n\
          #\n\
          # File Type Colors [theme.files]:\n\
          #   Use keys like 'python' (for .py), 'directory', 'executable'.\n\
@@ -152,6 +342,27 @@ pub fn save_config(config: &RootConfig) -> std::io::Result<()> {
          #     \"file.zip\" = \"pink\"\n\
          #     \"file.iso\" = \"pink\"\n\
          #   If you don't quote it (file.zip = ...), it will break the config!\n\
+         #   Multi-part extensions work too: \"file.tar.gz\" or \"tar.gz\".\n\
+         #   Glob keys match the whole name: \"Cargo.*\", \"*.tar.*\".\n\
+         #   Special keys: symlink, broken_symlink, fifo, socket, block_device,\n\
+         #   char_device, setuid, sticky (directories) and hidden (dotfiles).\n\
+         #   The most specific match wins: special types first, then globs,\n\
+         #   then the longest extension, then executable, hidden and default.\n\
+         #\n\
+         # Icons:\n\
+         #   Set icons = true in [config] (or run 'ls --icons') to show Nerd Font\n\
+         #   icons. Override one per key in [theme.icons], e.g. rs = \"R\".\n\
+         #\n\
+         # Git Status Colors [theme.git]:\n\
+         #   Inside a git repository ls marks entries as modified (M), staged (+),\n\
+         #   untracked (?), ignored (!) or conflicted (U). Directories show the\n\
+         #   most important status of anything inside them.\n\
+         #\n\
+         # Defaults and updates:\n\
+         #   Keys you leave out take their default values, and entries in\n\
+         #   [colors] and [theme.files] add to the defaults. version is the\n\
+         #   layout of this file; after an update an older file is rewritten\n\
+         #   (adding new keys) and the old one kept as config.toml.v<N>.bak.\n\
          #\n\
          {}\n",
         content
@@ -161,3 +372,21 @@ pub fn save_config(config: &RootConfig) -> std::io::Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_config_value_keeps_file() {
+        let content = "# mine\n[config]\neditor = \"vim\" # comment\ntheme = \"aero\"\n\n[colors]\npink = \"#FFC0CB\"\n";
+        let updated = with_config_value(content, "theme", "dracula").unwrap();
+        assert_eq!(updated, content.replace("\"aero\"", "\"dracula\""));
+        assert!(!updated.contains("version"));
+
+        let updated = with_config_value("[colors]\npink = \"#FFC0CB\"\n", "theme", "mono").unwrap();
+        assert_eq!(updated, "[colors]\npink = \"#FFC0CB\"\n\n[config]\ntheme = \"mono\"\n");
+
+        assert!(with_config_value("[config]\neditor = 5\n", "theme", "mono").is_err());
+    }
+}
@@ -0,0 +1,483 @@
+use std::env;
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use toml_edit::{ImDocument, Item};
+
+use crate::config::{get_config_path, RootConfig};
+use crate::context::CONTEXT_SEGMENTS;
+use crate::prompt::{self, Token, VARIABLES};
+use crate::segments::SLOW_SEGMENTS;
+use crate::theme::{self, ThemeFile, ThemeSource, BUILTIN_COLORS, STYLE_WORDS};
+
+/// Editors suggested when the configured one isn't installed.
+const COMMON_EDITORS: &[&str] = &["nano", "vim", "nvim", "vi", "micro", "hx", "emacs", "code"];
+
+/// Something wrong in config.toml, at a 1-based line and column.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Problem {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+    pub suggestion: Option<String>,
+}
+
+impl Problem {
+    fn at(content: &str, offset: usize, message: String, suggestion: Option<String>) -> Problem {
+        let before = &content[..offset.min(content.len())];
+        let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+        Problem {
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+            message,
+            suggestion,
+        }
+    }
+
+    /// `path:line:column: message`, with the suggestion on its own line.
+    pub fn display(&self, path: &Path) -> String {
+        let mut text = format!("{}:{}:{}: {}", path.display(), self.line, self.column, self.message);
+        if let Some(suggestion) = &self.suggestion {
+            text.push_str(&format!("\n    {}", suggestion));
+        }
+        text
+    }
+}
+
+/// Where and why toml (or a missing field) rejected the file.
+pub fn parse_error(content: &str, error: &toml::de::Error) -> Problem {
+    let offset = error.span().map(|span| span.start).unwrap_or(0);
+    Problem::at(content, offset, error.message().trim().replace('\n', "; "), None)
+}
+
+/// Checks a config or theme file that parsed: color references in
+/// `[theme]`, `[theme.files]`, `[theme.git]` and `[colors]`, and tags and
+/// variables in the prompt template. `config` supplies the custom color
+/// names, including those of the selected theme.
+pub fn check(content: &str, config: &RootConfig) -> Vec<Problem> {
+    let Ok(doc) = ImDocument::parse(content) else {
+        return Vec::new();
+    };
+    let mut checker = Checker { content, config, problems: Vec::new() };
+    let root = doc.as_table();
+
+    if let Some(section) = root.get("theme").and_then(Item::as_table_like) {
+        for (key, item) in section.iter() {
+            match key {
+                "prompt_template" => checker.template(item),
+                "files" | "git" => {
+                    for (_, item) in item.as_table_like().into_iter().flat_map(|t| t.iter()) {
+                        checker.color(item);
+                    }
+                }
+                // Icons are glyphs, not colors.
+                "icons" => {}
+                _ => checker.color(item),
+            }
+        }
+    }
+    if let Some(colors) = root.get("colors").and_then(Item::as_table_like) {
+        for (_, item) in colors.iter() {
+            checker.color(item);
+        }
+    }
+
+    checker.problems.sort_by_key(|p| (p.line, p.column));
+    checker.problems
+}
+
+/// Checks that `[config] editor` can be run. Only `aero config check` does
+/// this, so a missing editor isn't reported on every start.
+fn check_editor(content: &str, config: &RootConfig) -> Vec<Problem> {
+    let Ok(doc) = ImDocument::parse(content) else {
+        return Vec::new();
+    };
+    let mut checker = Checker { content, config, problems: Vec::new() };
+    if let Some(editor) = doc.as_table().get("config").and_then(|c| c.get("editor")) {
+        checker.editor(editor);
+    }
+    checker.problems
+}
+
+/// A file the config is built from.
+struct Source {
+    path: PathBuf,
+    content: String,
+    /// Where toml rejected the file, if it did.
+    parse_error: Option<Problem>,
+}
+
+/// config.toml, and the theme file `[config] theme` selects when it's one
+/// of the user's. Built-in themes are checked by the tests.
+fn sources(config: &RootConfig) -> Vec<Source> {
+    let mut sources = Vec::new();
+    let path = get_config_path();
+    if let Ok(content) = fs::read_to_string(&path) {
+        let parse_error = toml::from_str::<RootConfig>(&content).err().map(|e| parse_error(&content, &e));
+        sources.push(Source { path, content, parse_error });
+    }
+    let name = theme::selected_theme_name(config);
+    if !name.is_empty() {
+        if let Ok((content, ThemeSource::User(path))) = theme::theme_source(&name) {
+            let parse_error = toml::from_str::<ThemeFile>(&content).err().map(|e| parse_error(&content, &e));
+            sources.push(Source { path, content, parse_error });
+        }
+    }
+    sources
+}
+
+/// Prints what `check` finds in config.toml and the selected theme file as
+/// warnings. Files that don't parse were already reported while loading.
+pub fn warn(config: &RootConfig) {
+    for source in sources(config) {
+        if source.parse_error.is_some() {
+            continue;
+        }
+        for problem in check(&source.content, config) {
+            eprintln!("Warning: {}", problem.display(&source.path));
+        }
+    }
+}
+
+/// `aero config check`
+pub fn cmd_check(config: &RootConfig) {
+    let location_c = theme::resolve(&config.theme.subheader, config).prefix();
+    let hint = theme::resolve(&config.theme.autocomplete, config);
+    let active_c = theme::resolve(&config.theme.active, config).prefix();
+    let err_c = theme::resolve(&config.theme.disable, config).prefix();
+    let reset = theme::reset();
+
+    let config_path = get_config_path();
+    let sources = sources(config);
+    if !sources.iter().any(|s| s.path == config_path) {
+        eprintln!("{}{}: cannot be read{}", err_c, config_path.display(), reset);
+        return;
+    }
+
+    let mut total = 0;
+    for source in &sources {
+        let problems = match source.parse_error {
+            Some(ref error) => vec![error.clone()],
+            None => {
+                let mut problems = check(&source.content, config);
+                if source.path == config_path {
+                    problems.extend(check_editor(&source.content, config));
+                    problems.sort_by_key(|p| (p.line, p.column));
+                }
+                problems
+            }
+        };
+        total += problems.len();
+        for p in &problems {
+            println!("{}{}:{}:{}:{} {}", location_c, source.path.display(), p.line, p.column, reset, p.message);
+            if let Some(suggestion) = &p.suggestion {
+                println!("    {}", hint.paint(suggestion));
+            }
+        }
+    }
+
+    let checked: Vec<String> = sources.iter().map(|s| s.path.display().to_string()).collect();
+    if total == 0 {
+        println!("{}No problems found in {}.{}", active_c, checked.join(" and "), reset);
+        return;
+    }
+    println!("\n{}{} problem{} found.{}", err_c, total, if total == 1 { "" } else { "s" }, reset);
+}
+
+struct Checker<'a> {
+    content: &'a str,
+    config: &'a RootConfig,
+    problems: Vec<Problem>,
+}
+
+impl Checker<'_> {
+    fn push(&mut self, offset: usize, message: String, suggestion: Option<String>) {
+        self.problems.push(Problem::at(self.content, offset, message, suggestion));
+    }
+
+    /// Offset of `needle` in the value at `span`, searching from `*from` and
+    /// moving it past the match, so a word used twice in one value is
+    /// reported at each use. Falls back to `*from` when it isn't found.
+    fn find(&self, span: &Range<usize>, from: &mut usize, needle: &str) -> usize {
+        let found = self
+            .content
+            .get(*from..span.end)
+            .and_then(|raw| raw.find(needle))
+            .map(|i| *from + i);
+        match found {
+            Some(offset) => {
+                *from = offset + needle.len();
+                offset
+            }
+            None => *from,
+        }
+    }
+
+    fn color(&mut self, item: &Item) {
+        if let (Some(spec), Some(span)) = (item.as_str(), item.span()) {
+            let mut from = span.start;
+            self.spec(spec, &span, &mut from, false);
+        }
+    }
+
+    fn spec(&mut self, spec: &str, span: &Range<usize>, from: &mut usize, in_template: bool) {
+        for word in theme::split_spec(spec) {
+            let name = ["bg:", "on:", "fg:"].iter().find_map(|p| word.strip_prefix(p)).unwrap_or(word);
+            if let Some((message, mut suggestion)) = self.color_problem(name) {
+                if in_template && suggestion.is_none() {
+                    suggestion = Some("write !! for a literal '!' in the prompt".to_string());
+                }
+                let offset = self.find(span, from, word);
+                self.push(offset, message, suggestion);
+            }
+        }
+    }
+
+    fn color_problem(&self, word: &str) -> Option<(String, Option<String>)> {
+        if STYLE_WORDS.contains(&word) || self.config.colors.contains_key(word) || theme::parse_color(word).is_some() {
+            return None;
+        }
+        if word.starts_with('#') {
+            return Some((
+                format!("malformed hex color '{}'", word),
+                Some("use #RRGGBB or #RGB, e.g. #FF8800".to_string()),
+            ));
+        }
+        if word.starts_with("rgb(") {
+            return Some((
+                format!("malformed color '{}'", word),
+                Some("use rgb(r, g, b) with values from 0 to 255".to_string()),
+            ));
+        }
+        if word.bytes().all(|b| b.is_ascii_digit()) {
+            return Some((format!("color index {} is out of range", word), Some("use 0-255".to_string())));
+        }
+
+        let mut names: Vec<&str> = BUILTIN_COLORS.iter().map(|(name, _)| *name).collect();
+        names.extend(STYLE_WORDS);
+        names.extend(self.config.colors.keys().map(|k| k.as_str()));
+        let suggestion = closest(word, &names).map(|name| format!("did you mean '{}'?", name));
+        Some((format!("unknown color '{}'", word), suggestion))
+    }
+
+    fn template(&mut self, item: &Item) {
+        let (Some(template), Some(span)) = (item.as_str(), item.span()) else {
+            return;
+        };
+        let mut from = span.start;
+        for token in prompt::tokenize(template) {
+            match token {
+                Token::Tag(tag) => {
+                    // Start at this tag, so its words aren't matched in earlier text.
+                    let mut tag_from = from;
+                    let tag_start = self.find(&span, &mut tag_from, &format!("!{}!", tag));
+                    from = tag_start;
+                    self.spec(&tag, &span, &mut from, true);
+                    from = from.max(tag_from);
+                }
+                Token::Var(name) => {
+                    let mut known: Vec<&str> = VARIABLES.to_vec();
+                    known.extend(CONTEXT_SEGMENTS);
+                    known.extend(SLOW_SEGMENTS);
+                    if known.contains(&name.as_str()) {
+                        continue;
+                    }
+                    let var = format!("%{}%", name);
+                    let suggestion = closest(&name, &known).map(|n| format!("did you mean '%{}%'?", n));
+                    let offset = self.find(&span, &mut from, &var);
+                    self.push(offset, format!("unknown prompt variable '{}'", var), suggestion);
+                }
+                Token::Text(_) => {}
+            }
+        }
+    }
+
+    fn editor(&mut self, item: &Item) {
+        let (Some(editor), Some(span)) = (item.as_str(), item.span()) else {
+            return;
+        };
+        // The editor may carry arguments, like "code --wait".
+        let program = shlex::split(editor).and_then(|words| words.into_iter().next()).unwrap_or_default();
+        if program.is_empty() {
+            self.push(span.start, "editor is empty".to_string(), Some(installed_editors()));
+        } else if !on_path(&program) {
+            self.push(span.start, format!("editor '{}' was not found on PATH", program), Some(installed_editors()));
+        }
+    }
+}
+
+fn installed_editors() -> String {
+    let found: Vec<&str> = COMMON_EDITORS.iter().copied().filter(|e| on_path(e)).collect();
+    if found.is_empty() {
+        "install an editor or set editor to its full path".to_string()
+    } else {
+        format!("installed editors: {}", found.join(", "))
+    }
+}
+
+/// Whether `program` can be run: a path to an executable, or a name on PATH.
+fn on_path(program: &str) -> bool {
+    if program.contains('/') {
+        return is_executable(Path::new(program));
+    }
+    env::var_os("PATH").is_some_and(|path| env::split_paths(&path).any(|dir| is_executable(&dir.join(program))))
+}
+
+fn is_executable(path: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::metadata(path).is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+    }
+    #[cfg(not(unix))]
+    {
+        path.is_file()
+    }
+}
+
+/// The candidate within a small edit distance of `word`, if any.
+fn closest<'a>(word: &str, candidates: &[&'a str]) -> Option<&'a str> {
+    let limit = (word.chars().count() / 3).clamp(1, 2);
+    candidates
+        .iter()
+        .map(|c| (edit_distance(word, c), *c))
+        .filter(|(distance, _)| *distance <= limit)
+        .min()
+        .map(|(_, c)| c)
+}
+
+/// Edits (insert, delete, substitute, swap neighbours) turning `a` into `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    d[0] = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            d[i][j] = (d[i - 1][j] + 1).min(d[i][j - 1] + 1).min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r##"[config]
+username = "me"
+editor = "/definitely/not/an/editor"
+
+[theme]
+prompt_template = "!teal!%username%!pnik! %dirctory% !bold,#12345!> "
+autocomplete = "grey"
+typing = "lightpink"
+typingtext = "white"
+header = "bold,pnik"
+subheader = "purple"
+body = "white"
+active = "fg:green bg:300"
+disable = "red"
+
+[theme.files]
+directory = "blue"
+"*.rs" = "rgb(1, 2)"
+
+[colors]
+lightpink = "#FFB6C1"
+pink = "#FFC0CB"
+teal = "#008080"
+"##;
+
+    fn problems() -> Vec<Problem> {
+        let config: RootConfig = toml::from_str(CONFIG).unwrap();
+        check(CONFIG, &config)
+    }
+
+    #[test]
+    fn test_reports_positions_and_suggestions() {
+        let problems = problems();
+        let summary: Vec<(usize, usize, &str)> =
+            problems.iter().map(|p| (p.line, p.column, p.message.as_str())).collect();
+        assert_eq!(
+            summary,
+            vec![
+                (6, 37, "unknown color 'pnik'"),
+                (6, 43, "unknown prompt variable '%dirctory%'"),
+                (6, 60, "malformed hex color '#12345'"),
+                (10, 16, "unknown color 'pnik'"),
+                (13, 20, "color index 300 is out of range"),
+                (18, 11, "malformed color 'rgb(1, 2)'"),
+            ]
+        );
+        assert_eq!(problems[0].suggestion.as_deref(), Some("did you mean 'pink'?"));
+        assert_eq!(problems[1].suggestion.as_deref(), Some("did you mean '%directory%'?"));
+    }
+
+    #[test]
+    fn test_editor_checked_on_demand() {
+        let config: RootConfig = toml::from_str(CONFIG).unwrap();
+        let problems = check_editor(CONFIG, &config);
+        assert_eq!(problems.len(), 1);
+        assert_eq!((problems[0].line, problems[0].column), (3, 10));
+        assert_eq!(problems[0].message, "editor '/definitely/not/an/editor' was not found on PATH");
+    }
+
+    #[test]
+    fn test_repeated_words() {
+        let content = "[theme]\nheader = \"pnik pnik\"\nprompt_template = \"pnik !pnik!%x% !pnik!%x%\"\n";
+        let config: RootConfig = toml::from_str(content).unwrap();
+        let columns: Vec<(usize, usize)> = check(content, &config).iter().map(|p| (p.line, p.column)).collect();
+        assert_eq!(columns, vec![(2, 11), (2, 16), (3, 26), (3, 31), (3, 36), (3, 41)]);
+    }
+
+    #[test]
+    fn test_multibyte_hex() {
+        let content = "[theme]\nheader = \"#aéaaa\"\n";
+        let config: RootConfig = toml::from_str(content).unwrap();
+        let problems = check(content, &config);
+        assert_eq!(problems.len(), 1);
+        assert_eq!((problems[0].line, problems[0].column), (2, 11));
+        assert_eq!(problems[0].message, "malformed hex color '#aéaaa'");
+    }
+
+    #[test]
+    fn test_default_config_is_clean() {
+        let config = RootConfig::default();
+        let content = toml::to_string_pretty(&config).unwrap();
+        assert_eq!(check(&content, &config), Vec::new());
+    }
+
+    #[test]
+    fn test_builtin_themes_are_clean() {
+        for (name, content) in theme::BUILTIN_THEMES {
+            let mut config = RootConfig::default();
+            theme::apply_theme(&mut config, toml::from_str(content).unwrap());
+            assert_eq!(check(content, &config), Vec::new(), "theme {}", name);
+        }
+    }
+
+    #[test]
+    fn test_parse_error_position() {
+        let content = "[config]\nusername = \"me\"\neditor = 5\n";
+        let error = toml::from_str::<RootConfig>(content).unwrap_err();
+        let problem = parse_error(content, &error);
+        assert_eq!((problem.line, problem.column), (3, 10));
+    }
+
+    #[test]
+    fn test_closest() {
+        assert_eq!(closest("pnik", &["pink", "purple"]), Some("pink"));
+        assert_eq!(closest("zzz", &["pink", "purple"]), None);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("pnik", "pink"), 1);
+    }
+}
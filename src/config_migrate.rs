@@ -0,0 +1,228 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use toml_edit::{value, DocumentMut, Item, Table};
+
+use crate::config::RootConfig;
+
+/// The config layout this build writes. When a key is renamed, moved or
+/// changes meaning, bump this and append a step to `MIGRATIONS`.
+pub const CONFIG_VERSION: u32 = 1;
+
+/// `MIGRATIONS[n]` rewrites a version `n` config into version `n + 1`.
+const MIGRATIONS: &[fn(&mut DocumentMut)] = &[add_missing_keys];
+
+/// What `migrate_file` did.
+#[derive(Debug, PartialEq)]
+pub struct Migrated {
+    pub from: u32,
+    pub backup: PathBuf,
+}
+
+/// Brings the config at `path` up to `CONFIG_VERSION` in place, copying the
+/// old file to `config.toml.v<old>.bak` first. Configs that are current, or
+/// from a newer build, are left alone; unknown keys are ignored on load.
+/// So are files that don't parse, which loading reports.
+pub fn migrate_file(path: &Path) -> Result<Option<Migrated>, String> {
+    let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let Ok(Some((from, migrated))) = migrate(&content) else {
+        return Ok(None);
+    };
+    let backup = path.with_extension(format!("toml.v{}.bak", from));
+    fs::copy(path, &backup).map_err(|e| format!("backup to {}: {}", backup.display(), e))?;
+    fs::write(path, migrated).map_err(|e| e.to_string())?;
+    Ok(Some(Migrated { from, backup }))
+}
+
+/// The old version and the rewritten file, or `None` when there's nothing
+/// to do. Comments and layout are kept.
+fn migrate(content: &str) -> Result<Option<(u32, String)>, String> {
+    let mut doc: DocumentMut = content.parse().map_err(|e: toml_edit::TomlError| e.message().to_string())?;
+    let from = match doc.get("version") {
+        None => 0,
+        Some(item) => item
+            .as_integer()
+            .and_then(|v| u32::try_from(v).ok())
+            .ok_or("version must be a whole number")?,
+    };
+    if from >= CONFIG_VERSION {
+        return Ok(None);
+    }
+    for step in &MIGRATIONS[from as usize..] {
+        step(&mut doc);
+    }
+    set_version(&mut doc);
+    Ok(Some((from, doc.to_string())))
+}
+
+/// 0 -> 1: configs from before versioning lack the keys added since, so
+/// write them out with their defaults where the user can see them. Map
+/// entries such as `[colors]` are filled in the same way; the user's own
+/// entries are kept as they are.
+fn add_missing_keys(doc: &mut DocumentMut) {
+    let defaults = toml::to_string(&RootConfig::default()).unwrap_or_default();
+    let Ok(defaults) = defaults.parse::<DocumentMut>() else {
+        return;
+    };
+    for section in ["config", "theme", "colors"] {
+        let Some(default) = defaults.get(section).and_then(Item::as_table) else {
+            continue;
+        };
+        let root = doc.as_table_mut();
+        if !root.contains_key(section) {
+            root.insert(section, Item::Table(Table::new()));
+        }
+        if let Some(table) = root.get_mut(section).and_then(Item::as_table_mut) {
+            fill(table, default);
+        }
+    }
+}
+
+fn fill(table: &mut Table, defaults: &Table) {
+    for (key, default) in defaults.iter() {
+        match (table.get_mut(key), default) {
+            (None, Item::Value(v)) => {
+                table.insert(key, Item::Value(v.clone()));
+            }
+            // Nothing to add, e.g. `[theme.icons]`
+            (None, Item::Table(t)) if t.is_empty() => {}
+            (None, Item::Table(t)) => {
+                // A fresh table, so it's placed after its parent rather than
+                // at the defaults' position.
+                let mut new = Table::new();
+                fill(&mut new, t);
+                table.insert(key, Item::Table(new));
+            }
+            (Some(Item::Table(existing)), Item::Table(t)) => fill(existing, t),
+            _ => {}
+        }
+    }
+}
+
+fn set_version(doc: &mut DocumentMut) {
+    let root = doc.as_table_mut();
+    let is_new = !root.contains_key("version");
+    root.insert("version", value(i64::from(CONFIG_VERSION)));
+    if !is_new {
+        return;
+    }
+    // Root keys are written above every table, so move the file's leading
+    // comment up to keep it on top.
+    let first = root
+        .iter()
+        .filter_map(|(key, item)| Some((item.as_table()?.position()?, key.to_string())))
+        .min()
+        .map(|(_, key)| key);
+    let Some(first) = first else {
+        return;
+    };
+    let Some(table) = root.get_mut(&first).and_then(Item::as_table_mut) else {
+        return;
+    };
+    let comment = table.decor().prefix().and_then(|p| p.as_str()).unwrap_or_default().to_string();
+    table.decor_mut().set_prefix("\n");
+    if let Some(mut key) = root.key_mut("version") {
+        key.leaf_decor_mut().set_prefix(comment);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OLD: &str = r##"# AeroShell Configuration
+# keep me
+
+[config]
+username = "me"
+editor = "vim"
+
+[theme]
+prompt_template = "%username% > "
+autocomplete = "grey"
+typing = "pink"
+typingtext = "white"
+header = "pink"
+subheader = "purple"
+body = "white"
+active = "green"
+disable = "red"
+
+[theme.files]
+directory = "blue"
+
+[colors]
+pink = "#FFC0CB"
+"##;
+
+    #[test]
+    fn test_migrates_unversioned_config() {
+        let (from, migrated) = migrate(OLD).unwrap().unwrap();
+        assert_eq!(from, 0);
+        assert!(migrated.starts_with("# AeroShell Configuration\n# keep me\n\nversion = 1\n\n[config]\n"));
+        assert!(migrated.contains("editor = \"vim\"\nduration_threshold_ms = 2000\n"));
+        assert!(migrated.contains("theme_dark = \"aero\"\nicons = false\n"));
+        assert!(migrated.contains("[theme.git]\nmodified = \"yellow\""));
+        // Maps keep the user's entries and gain the missing defaults.
+        assert!(migrated.contains("[theme.files]\ndirectory = \"blue\"\n"));
+        assert!(migrated.contains("\nsymlink = \"teal\"\n"));
+        assert!(migrated.contains("[colors]\npink = \"#FFC0CB\"\n"));
+        assert!(migrated.contains("\nlime = \"#00FF00\"\n"));
+        assert!(!migrated.contains("[theme.icons]"));
+
+        let config: RootConfig = toml::from_str(&migrated).unwrap();
+        let defaults = RootConfig::default();
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert_eq!(config.config.editor, "vim");
+        assert_eq!(config.theme.files, defaults.theme.files);
+        assert_eq!(config.colors, defaults.colors);
+
+        assert_eq!(migrate(&migrated).unwrap(), None);
+    }
+
+    #[test]
+    fn test_partial_config_uses_defaults() {
+        let config: RootConfig = toml::from_str("[config]\neditor = \"vim\"\n").unwrap();
+        let defaults = RootConfig::default();
+        assert_eq!(config.version, 0);
+        assert_eq!(config.config.editor, "vim");
+        assert_eq!(config.config.duration_threshold_ms, defaults.config.duration_threshold_ms);
+        assert_eq!(config.theme.prompt_template, defaults.theme.prompt_template);
+        assert_eq!(config.colors, defaults.colors);
+    }
+
+    #[test]
+    fn test_partial_maps_merge_over_defaults() {
+        let config: RootConfig =
+            toml::from_str("[theme.files]\nrust = \"bold\"\n\n[colors]\nmine = \"#010203\"\n").unwrap();
+        assert_eq!(config.colors["mine"], "#010203");
+        assert_eq!(config.theme.files["rust"], "bold");
+        assert_eq!(config.theme.files["directory"], "blue");
+        // The default theme's colors still resolve.
+        let teal = crate::theme::resolve("teal", &config);
+        assert_eq!(teal.fg, Some(crate::theme::Color::Rgb(0, 128, 128)));
+    }
+
+    #[test]
+    fn test_newer_config_is_left_alone() {
+        let newer = format!("version = {}\n[config]\nfuture_key = 1\n", CONFIG_VERSION + 1);
+        assert_eq!(migrate(&newer).unwrap(), None);
+        assert!(toml::from_str::<RootConfig>(&newer).is_ok());
+    }
+
+    #[test]
+    fn test_migrate_file_keeps_backup() {
+        let dir = std::env::temp_dir().join(format!("aeroshell-migrate-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        fs::write(&path, OLD).unwrap();
+
+        let migrated = migrate_file(&path).unwrap().unwrap();
+        assert_eq!(migrated, Migrated { from: 0, backup: dir.join("config.toml.v0.bak") });
+        assert_eq!(fs::read_to_string(&migrated.backup).unwrap(), OLD);
+        assert!(fs::read_to_string(&path).unwrap().contains("version = 1"));
+        assert_eq!(migrate_file(&path).unwrap(), None);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
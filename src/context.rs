@@ -0,0 +1,207 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Fast prompt variables describing the language/environment context.
+/// Everything here is read from files or environment variables, never by
+/// spawning tools, because it runs before every prompt.
+pub const CONTEXT_SEGMENTS: &[&str] = &[
+    "rust_toolchain",
+    "python_venv",
+    "node_version",
+    "nix_shell",
+    "container",
+    "ssh",
+];
+
+pub fn context_segment(name: &str, dir: &Path) -> String {
+    match name {
+        "rust_toolchain" => rust_toolchain(dir),
+        "python_venv" => python_venv(),
+        "node_version" => node_version(dir),
+        "nix_shell" => nix_shell(),
+        "container" => container(Path::new("/")),
+        "ssh" => ssh(),
+        _ => String::new(),
+    }
+}
+
+// Walks from `dir` up to the filesystem root looking for the first of `names`.
+fn find_upwards(dir: &Path, names: &[&str]) -> Option<PathBuf> {
+    dir.ancestors()
+        .flat_map(|d| names.iter().map(move |n| d.join(n)))
+        .find(|p| p.is_file())
+}
+
+fn rust_toolchain(dir: &Path) -> String {
+    find_upwards(dir, &["rust-toolchain.toml", "rust-toolchain"])
+        .and_then(|p| fs::read_to_string(p).ok())
+        .map(|content| parse_rust_toolchain(&content))
+        .unwrap_or_default()
+}
+
+/// Reads the channel from `rust-toolchain.toml`, or from a legacy
+/// `rust-toolchain` file that holds just the channel name.
+fn parse_rust_toolchain(content: &str) -> String {
+    if let Ok(value) = content.parse::<toml::Table>() {
+        if let Some(channel) = value
+            .get("toolchain")
+            .and_then(|t| t.get("channel"))
+            .and_then(|c| c.as_str())
+        {
+            return channel.to_string();
+        }
+    }
+    content
+        .lines()
+        .map(|l| l.trim())
+        .find(|l| !l.is_empty() && !l.starts_with('#') && !l.starts_with('['))
+        .filter(|l| !l.contains('='))
+        .unwrap_or("")
+        .to_string()
+}
+
+fn python_venv() -> String {
+    env::var("VIRTUAL_ENV")
+        .ok()
+        .filter(|v| !v.is_empty())
+        .and_then(|v| Path::new(&v).file_name().map(|n| n.to_string_lossy().to_string()))
+        .unwrap_or_default()
+}
+
+fn node_version(dir: &Path) -> String {
+    find_upwards(dir, &[".nvmrc", ".node-version"])
+        .and_then(|p| fs::read_to_string(p).ok())
+        .and_then(|c| c.lines().next().map(|l| l.trim().to_string()))
+        .unwrap_or_default()
+}
+
+fn nix_shell() -> String {
+    match env::var("IN_NIX_SHELL") {
+        Ok(kind) if !kind.is_empty() => {
+            // `nix develop` sets $name to the derivation name; fall back to pure/impure.
+            env::var("name").ok().filter(|n| !n.is_empty()).unwrap_or(kind)
+        }
+        _ => String::new(),
+    }
+}
+
+fn container(root: &Path) -> String {
+    if root.join(".dockerenv").exists() {
+        return "docker".to_string();
+    }
+    if root.join("run/.containerenv").exists() {
+        return "podman".to_string();
+    }
+    // systemd-nspawn, lxc and toolbox/distrobox set $container
+    env::var("container").unwrap_or_default()
+}
+
+fn ssh() -> String {
+    let in_ssh = ["SSH_CONNECTION", "SSH_CLIENT", "SSH_TTY"]
+        .iter()
+        .any(|v| env::var(v).map(|s| !s.is_empty()).unwrap_or(false));
+    if in_ssh {
+        "ssh".to_string()
+    } else {
+        String::new()
+    }
+}
+
+/// Short host name from the system (uname), not `$HOSTNAME`, which most
+/// shells never export.
+pub fn hostname() -> String {
+    sysinfo::System::host_name()
+        .or_else(|| env::var("HOSTNAME").ok())
+        .map(|h| h.split('.').next().unwrap_or(&h).to_string())
+        .filter(|h| !h.is_empty())
+        .unwrap_or_else(|| "localhost".to_string())
+}
+
+/// Name of the real (login) user, resolved from the uid rather than config.
+pub fn login_user() -> String {
+    #[cfg(unix)]
+    {
+        if let Some(name) = user_name_for_uid(unsafe { libc::getuid() }) {
+            return name;
+        }
+    }
+    env::var("USER")
+        .or_else(|_| env::var("LOGNAME"))
+        .unwrap_or_else(|_| "user".to_string())
+}
+
+#[cfg(unix)]
+pub fn user_name_for_uid(uid: u32) -> Option<String> {
+    use std::ffi::CStr;
+
+    let mut buf = vec![0 as libc::c_char; 1024];
+    let mut pwd: libc::passwd = unsafe { std::mem::zeroed() };
+    let mut result: *mut libc::passwd = std::ptr::null_mut();
+    let rc = unsafe {
+        libc::getpwuid_r(uid, &mut pwd, buf.as_mut_ptr(), buf.len(), &mut result)
+    };
+    if rc != 0 || result.is_null() {
+        return None;
+    }
+    let name = unsafe { CStr::from_ptr(pwd.pw_name) };
+    Some(name.to_string_lossy().to_string())
+}
+
+#[cfg(unix)]
+pub fn group_name_for_gid(gid: u32) -> Option<String> {
+    use std::ffi::CStr;
+
+    let mut buf = vec![0 as libc::c_char; 4096];
+    let mut grp: libc::group = unsafe { std::mem::zeroed() };
+    let mut result: *mut libc::group = std::ptr::null_mut();
+    let rc = unsafe {
+        libc::getgrgid_r(gid, &mut grp, buf.as_mut_ptr(), buf.len(), &mut result)
+    };
+    if rc != 0 || result.is_null() {
+        return None;
+    }
+    let name = unsafe { CStr::from_ptr(grp.gr_name) };
+    Some(name.to_string_lossy().to_string())
+}
+
+/// True when running with an effective uid of 0.
+pub fn is_root() -> bool {
+    #[cfg(unix)]
+    {
+        unsafe { libc::geteuid() == 0 }
+    }
+    #[cfg(not(unix))]
+    {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rust_toolchain() {
+        assert_eq!(parse_rust_toolchain("[toolchain]\nchannel = \"1.79.0\"\n"), "1.79.0");
+        assert_eq!(parse_rust_toolchain("nightly-2024-05-01\n"), "nightly-2024-05-01");
+        assert_eq!(parse_rust_toolchain("[toolchain]\ncomponents = [\"rustfmt\"]\n"), "");
+    }
+
+    #[test]
+    fn test_files_found_upwards() {
+        let root = env::temp_dir().join(format!("aeroshell_ctx_{}", std::process::id()));
+        let nested = root.join("a/b");
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(root.join(".nvmrc"), "v20.11.0\n").unwrap();
+        fs::write(root.join("a/rust-toolchain.toml"), "[toolchain]\nchannel = \"stable\"\n").unwrap();
+        fs::create_dir_all(root.join("run")).unwrap();
+        fs::write(root.join("run/.containerenv"), "").unwrap();
+
+        assert_eq!(node_version(&nested), "v20.11.0");
+        assert_eq!(rust_toolchain(&nested), "stable");
+        assert_eq!(container(&root), "podman");
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,274 @@
+use std::collections::HashMap;
+use std::fs::{self, Metadata};
+use std::path::Path;
+#[cfg(unix)]
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+
+use crate::gitignore::glob_match;
+
+/// `theme.files` keys that name a language rather than an extension.
+pub const LEGACY_EXTENSIONS: &[(&str, &str)] = &[
+    ("python", "py"),
+    ("shellscript", "sh"),
+    ("rust", "rs"),
+    ("javascript", "js"),
+];
+
+/// Nerd Font icons used when `[config] icons = true`, looked up with the
+/// same keys as `theme.files`. `[theme.icons]` overrides any of them.
+const DEFAULT_ICONS: &[(&str, &str)] = &[
+    ("broken_symlink", "\u{f127}"),
+    ("symlink", "\u{f0c1}"),
+    ("fifo", "\u{f0ec}"),
+    ("socket", "\u{f1e6}"),
+    ("block_device", "\u{f0a0}"),
+    ("char_device", "\u{f11c}"),
+    ("directory", "\u{f07b}"),
+    ("executable", "\u{f489}"),
+    ("rust", "\u{e7a8}"),
+    ("python", "\u{e606}"),
+    ("javascript", "\u{e74e}"),
+    ("shellscript", "\u{f489}"),
+    ("ts", "\u{e628}"),
+    ("go", "\u{e627}"),
+    ("c", "\u{e61e}"),
+    ("h", "\u{e61e}"),
+    ("cpp", "\u{e61d}"),
+    ("java", "\u{e738}"),
+    ("rb", "\u{e739}"),
+    ("html", "\u{e736}"),
+    ("css", "\u{e749}"),
+    ("json", "\u{e60b}"),
+    ("toml", "\u{e615}"),
+    ("yaml", "\u{e615}"),
+    ("yml", "\u{e615}"),
+    ("lock", "\u{f023}"),
+    ("md", "\u{e609}"),
+    ("txt", "\u{f15c}"),
+    ("pdf", "\u{f1c1}"),
+    ("png", "\u{f1c5}"),
+    ("jpg", "\u{f1c5}"),
+    ("svg", "\u{f1c5}"),
+    ("zip", "\u{f410}"),
+    ("gz", "\u{f410}"),
+    ("xz", "\u{f410}"),
+    ("tar", "\u{f410}"),
+    ("iso", "\u{f0a0}"),
+    ("default", "\u{f15b}"),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    File,
+    Directory,
+    Fifo,
+    Socket,
+    BlockDevice,
+    CharDevice,
+}
+
+/// What `ls` needs to know about an entry to pick its color and icon.
+#[derive(Debug)]
+pub struct FileClass<'a> {
+    pub name: &'a str,
+    /// Type of the entry, or of its target for symlinks.
+    pub kind: Kind,
+    pub symlink: bool,
+    pub broken: bool,
+    pub executable: bool,
+    /// setuid or setgid
+    pub setuid: bool,
+    pub sticky: bool,
+}
+
+impl<'a> FileClass<'a> {
+    /// `metadata` is the lstat() result for `path`.
+    pub fn new(name: &'a str, path: &Path, metadata: Option<&Metadata>) -> FileClass<'a> {
+        let symlink = metadata.is_some_and(|m| m.file_type().is_symlink());
+        let target = if symlink { fs::metadata(path).ok() } else { metadata.cloned() };
+        let mut class = FileClass {
+            name,
+            kind: Kind::File,
+            symlink,
+            broken: symlink && target.is_none(),
+            executable: false,
+            setuid: false,
+            sticky: false,
+        };
+        let Some(m) = target else {
+            return class;
+        };
+
+        let file_type = m.file_type();
+        class.kind = if file_type.is_dir() { Kind::Directory } else { Kind::File };
+        #[cfg(unix)]
+        {
+            if file_type.is_fifo() {
+                class.kind = Kind::Fifo;
+            } else if file_type.is_socket() {
+                class.kind = Kind::Socket;
+            } else if file_type.is_block_device() {
+                class.kind = Kind::BlockDevice;
+            } else if file_type.is_char_device() {
+                class.kind = Kind::CharDevice;
+            }
+            let mode = m.permissions().mode();
+            class.executable = class.kind == Kind::File && mode & 0o111 != 0;
+            class.setuid = class.kind == Kind::File && mode & 0o6000 != 0;
+            class.sticky = class.kind == Kind::Directory && mode & 0o1000 != 0;
+        }
+        class
+    }
+
+    /// Keys to look up in `theme.files`, most specific first. `globs` are
+    /// the pattern keys (like `"Cargo.*"`) the theme defines.
+    pub fn keys(&self, globs: &[&str]) -> Vec<String> {
+        let mut keys = Vec::new();
+        if self.broken {
+            keys.push("broken_symlink".to_string());
+        }
+        if self.symlink {
+            keys.push("symlink".to_string());
+        }
+        match self.kind {
+            Kind::Fifo => keys.push("fifo".to_string()),
+            Kind::Socket => keys.push("socket".to_string()),
+            Kind::BlockDevice => keys.push("block_device".to_string()),
+            Kind::CharDevice => keys.push("char_device".to_string()),
+            Kind::Directory => {
+                if self.sticky {
+                    keys.push("sticky".to_string());
+                }
+                keys.push("directory".to_string());
+            }
+            Kind::File => {
+                if self.setuid {
+                    keys.push("setuid".to_string());
+                }
+                keys.extend(globs.iter().filter(|g| glob_match(g, self.name)).map(|g| g.to_string()));
+                for suffix in extensions(self.name) {
+                    keys.push(format!("file.{}", suffix));
+                    keys.push(suffix.to_string());
+                    if let Some((legacy, _)) = LEGACY_EXTENSIONS.iter().find(|(_, ext)| *ext == suffix) {
+                        keys.push(legacy.to_string());
+                    }
+                }
+                if self.executable {
+                    keys.push("executable".to_string());
+                }
+            }
+        }
+        if self.name.starts_with('.') && self.name != "." && self.name != ".." {
+            keys.push("hidden".to_string());
+        }
+        keys.push("default".to_string());
+        keys
+    }
+}
+
+/// Every extension of `name`, longest first: `a.tar.gz` gives `tar.gz`,
+/// then `gz`. The leading dot of a dotfile doesn't start an extension.
+pub fn extensions(name: &str) -> Vec<&str> {
+    let body = name.strip_prefix('.').unwrap_or(name);
+    body.char_indices()
+        .filter(|(_, c)| *c == '.')
+        .map(|(i, _)| &body[i + 1..])
+        .filter(|ext| !ext.is_empty())
+        .collect()
+}
+
+/// True for `theme.files` keys that are glob patterns rather than names.
+pub fn is_glob_key(key: &str) -> bool {
+    key.contains(['*', '?', '['])
+}
+
+/// Glob keys from `files`, longest (most specific) first.
+pub fn glob_keys(files: &HashMap<String, String>) -> Vec<&str> {
+    let mut globs: Vec<&str> = files.keys().map(|k| k.as_str()).filter(|k| is_glob_key(k)).collect();
+    globs.sort_by(|a, b| b.len().cmp(&a.len()).then(a.cmp(b)));
+    globs
+}
+
+pub fn icon<'a>(keys: &[String], overrides: &'a HashMap<String, String>) -> &'a str {
+    keys.iter()
+        .find_map(|key| {
+            overrides.get(key).map(|s| s.as_str()).or_else(|| {
+                DEFAULT_ICONS.iter().find(|(name, _)| name == key).map(|(_, icon)| *icon)
+            })
+        })
+        .unwrap_or("")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(name: &str) -> FileClass<'_> {
+        FileClass {
+            name,
+            kind: Kind::File,
+            symlink: false,
+            broken: false,
+            executable: false,
+            setuid: false,
+            sticky: false,
+        }
+    }
+
+    #[test]
+    fn test_extensions() {
+        assert_eq!(extensions("backup.tar.gz"), vec!["tar.gz", "gz"]);
+        assert_eq!(extensions(".bashrc"), Vec::<&str>::new());
+        assert_eq!(extensions(".config.toml"), vec!["toml"]);
+        assert_eq!(extensions("Makefile"), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn test_keys_order() {
+        let keys = file("backup.tar.gz").keys(&["*.tar.gz"]);
+        assert_eq!(keys, vec!["*.tar.gz", "file.tar.gz", "tar.gz", "file.gz", "gz", "default"]);
+
+        let keys = FileClass { executable: true, ..file("build.sh") }.keys(&[]);
+        assert_eq!(keys, vec!["file.sh", "sh", "shellscript", "executable", "default"]);
+
+        let keys = FileClass { symlink: true, broken: true, ..file("old") }.keys(&[]);
+        assert_eq!(&keys[..2], ["broken_symlink", "symlink"]);
+
+        let keys = FileClass { kind: Kind::Directory, sticky: true, ..file(".cache") }.keys(&["*"]);
+        assert_eq!(keys, vec!["sticky", "directory", "hidden", "default"]);
+
+        let keys = FileClass { setuid: true, executable: true, ..file("sudo") }.keys(&["Cargo.*"]);
+        assert_eq!(keys, vec!["setuid", "executable", "default"]);
+    }
+
+    #[test]
+    fn test_icons() {
+        let mut overrides = HashMap::new();
+        assert_eq!(icon(&file("main.rs").keys(&[]), &overrides), "\u{e7a8}");
+        assert_eq!(icon(&file("notes").keys(&[]), &overrides), "\u{f15b}");
+        overrides.insert("rs".to_string(), "R".to_string());
+        assert_eq!(icon(&file("main.rs").keys(&[]), &overrides), "R");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_classifies_real_files() {
+        let dir = std::env::temp_dir().join(format!("aeroshell_class_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("target"), "").unwrap();
+        std::os::unix::fs::symlink("target", dir.join("good")).unwrap();
+        std::os::unix::fs::symlink("missing", dir.join("bad")).unwrap();
+
+        let class = |name: &'static str| {
+            let path = dir.join(name);
+            let metadata = fs::symlink_metadata(&path).ok();
+            let class = FileClass::new(name, &path, metadata.as_ref());
+            (class.symlink, class.broken)
+        };
+        assert_eq!(class("target"), (false, false));
+        assert_eq!(class("good"), (true, false));
+        assert_eq!(class("bad"), (true, true));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,194 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::config::GitColors;
+
+/// Status of a path in the work tree, ordered by how much it needs
+/// attention: a directory shows the highest status found inside it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FileStatus {
+    Ignored,
+    Untracked,
+    Staged,
+    Modified,
+    Conflicted,
+}
+
+impl FileStatus {
+    pub fn marker(self) -> char {
+        match self {
+            FileStatus::Ignored => '!',
+            FileStatus::Untracked => '?',
+            FileStatus::Staged => '+',
+            FileStatus::Modified => 'M',
+            FileStatus::Conflicted => 'U',
+        }
+    }
+
+    /// Name used in `ls --json` and `ls --tsv`.
+    pub fn label(self) -> &'static str {
+        match self {
+            FileStatus::Ignored => "ignored",
+            FileStatus::Untracked => "untracked",
+            FileStatus::Staged => "staged",
+            FileStatus::Modified => "modified",
+            FileStatus::Conflicted => "conflicted",
+        }
+    }
+
+    pub fn color(self, colors: &GitColors) -> &str {
+        match self {
+            FileStatus::Ignored => &colors.ignored,
+            FileStatus::Untracked => &colors.untracked,
+            FileStatus::Staged => &colors.staged,
+            FileStatus::Modified => &colors.modified,
+            FileStatus::Conflicted => &colors.conflicted,
+        }
+    }
+}
+
+/// `git status` for the part of a repository being listed.
+pub struct RepoStatus {
+    root: PathBuf,
+    /// Paths relative to `root`. Untracked and ignored directories are
+    /// reported once, for the whole directory.
+    entries: HashMap<PathBuf, FileStatus>,
+    /// Highest status below each directory (ignored files don't count).
+    rollup: HashMap<PathBuf, FileStatus>,
+}
+
+impl RepoStatus {
+    /// Status of everything under `dir`, or `None` outside a repository
+    /// or when git isn't installed.
+    pub fn load(dir: &Path) -> Option<RepoStatus> {
+        let root = run_git(dir, &["rev-parse", "--show-toplevel"])?;
+        let root = PathBuf::from(root.trim_end_matches('\n'));
+        let status = run_git(dir, &["status", "--porcelain=v1", "-z", "--ignored", "--", "."])?;
+        Some(RepoStatus::from_entries(root, parse_porcelain(&status)))
+    }
+
+    fn from_entries(root: PathBuf, list: Vec<(PathBuf, FileStatus)>) -> RepoStatus {
+        let mut entries = HashMap::new();
+        let mut rollup: HashMap<PathBuf, FileStatus> = HashMap::new();
+        for (path, status) in list {
+            if status != FileStatus::Ignored {
+                for dir in path.ancestors().skip(1) {
+                    let current = rollup.entry(dir.to_path_buf()).or_insert(status);
+                    *current = (*current).max(status);
+                }
+            }
+            entries.insert(path, status);
+        }
+        RepoStatus { root, entries, rollup }
+    }
+
+    /// Status for `path` (relative to the current directory or absolute).
+    pub fn status(&self, path: &Path, is_dir: bool) -> Option<FileStatus> {
+        // Resolve the parent only, so a symlink is looked up as itself.
+        let absolute = match (path.parent(), path.file_name()) {
+            (Some(parent), Some(name)) => {
+                let parent = if parent.as_os_str().is_empty() { Path::new(".") } else { parent };
+                fs::canonicalize(parent).ok()?.join(name)
+            }
+            _ => fs::canonicalize(path).ok()?,
+        };
+        let relative = absolute.strip_prefix(&self.root).ok()?;
+        self.status_relative(relative, is_dir)
+    }
+
+    fn status_relative(&self, relative: &Path, is_dir: bool) -> Option<FileStatus> {
+        // Inside an untracked or ignored directory, everything shares its status.
+        let own = relative.ancestors().find_map(|p| self.entries.get(p)).copied();
+        let below = if is_dir { self.rollup.get(relative).copied() } else { None };
+        own.max(below)
+    }
+}
+
+fn run_git(dir: &Path, args: &[&str]) -> Option<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    output.status.success().then(|| String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Parses `git status --porcelain=v1 -z`: NUL-separated `XY path` records,
+/// where renames and copies are followed by an extra record with the old path.
+fn parse_porcelain(output: &str) -> Vec<(PathBuf, FileStatus)> {
+    let mut result = Vec::new();
+    let mut records = output.split('\0');
+    while let Some(record) = records.next() {
+        let mut chars = record.chars();
+        let (Some(x), Some(y), Some(' ')) = (chars.next(), chars.next(), chars.next()) else {
+            continue;
+        };
+        if matches!(x, 'R' | 'C') {
+            records.next();
+        }
+        if let Some(status) = classify(x, y) {
+            let path = chars.as_str().trim_end_matches('/');
+            result.push((PathBuf::from(path), status));
+        }
+    }
+    result
+}
+
+fn classify(index: char, worktree: char) -> Option<FileStatus> {
+    match (index, worktree) {
+        ('?', '?') => Some(FileStatus::Untracked),
+        ('!', '!') => Some(FileStatus::Ignored),
+        ('U', _) | (_, 'U') | ('A', 'A') | ('D', 'D') => Some(FileStatus::Conflicted),
+        (_, 'M' | 'D' | 'T') => Some(FileStatus::Modified),
+        (' ', _) => None,
+        _ => Some(FileStatus::Staged),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_porcelain() {
+        let output = " M src/main.rs\0M  README.md\0R  new.rs\0old.rs\0?? notes/\0!! target/\0UU merge.txt\0MM both.rs\0";
+        let parsed = parse_porcelain(output);
+        let expected = [
+            ("src/main.rs", FileStatus::Modified),
+            ("README.md", FileStatus::Staged),
+            ("new.rs", FileStatus::Staged),
+            ("notes", FileStatus::Untracked),
+            ("target", FileStatus::Ignored),
+            ("merge.txt", FileStatus::Conflicted),
+            ("both.rs", FileStatus::Modified),
+        ];
+        assert_eq!(parsed.len(), expected.len());
+        for ((path, status), (want_path, want_status)) in parsed.iter().zip(expected) {
+            assert_eq!(path, Path::new(want_path));
+            assert_eq!(*status, want_status);
+        }
+    }
+
+    #[test]
+    fn test_status_rolls_up() {
+        let repo = RepoStatus::from_entries(
+            PathBuf::from("/repo"),
+            parse_porcelain("A  src/lib.rs\0 M src/ui/view.rs\0?? docs/\0!! src/gen/\0"),
+        );
+        let status = |p: &str, dir| repo.status_relative(Path::new(p), dir);
+
+        assert_eq!(status("src", true), Some(FileStatus::Modified));
+        assert_eq!(status("src/ui", true), Some(FileStatus::Modified));
+        assert_eq!(status("src/lib.rs", false), Some(FileStatus::Staged));
+        assert_eq!(status("docs/guide.md", false), Some(FileStatus::Untracked));
+        assert_eq!(status("src/gen/out.rs", false), Some(FileStatus::Ignored));
+        assert_eq!(status("Cargo.toml", false), None);
+        // The repository root itself rolls up everything
+        assert_eq!(status("", true), Some(FileStatus::Modified));
+    }
+}
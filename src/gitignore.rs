@@ -0,0 +1,224 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// One line of a `.gitignore` file.
+struct Rule {
+    pattern: String,
+    negated: bool,
+    dir_only: bool,
+    /// Patterns containing a `/` match the path relative to `base`;
+    /// others match the file name at any depth.
+    anchored: bool,
+    base: PathBuf,
+}
+
+impl Rule {
+    fn parse(line: &str, base: &Path) -> Option<Rule> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        let (negated, line) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line.strip_prefix('\\').unwrap_or(line)),
+        };
+        let (dir_only, line) = match line.strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        if line.is_empty() {
+            return None;
+        }
+        let anchored = line.contains('/');
+        Some(Rule {
+            pattern: line.trim_start_matches('/').to_string(),
+            negated,
+            dir_only,
+            anchored,
+            base: base.to_path_buf(),
+        })
+    }
+
+    fn matches(&self, path: &Path, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        let Ok(relative) = path.strip_prefix(&self.base) else {
+            return false;
+        };
+        if self.anchored {
+            glob_match(&self.pattern, &relative.to_string_lossy())
+        } else {
+            path.file_name()
+                .is_some_and(|name| glob_match(&self.pattern, &name.to_string_lossy()))
+        }
+    }
+}
+
+/// The `.gitignore` rules in effect for a directory. Rules are stacked as a
+/// walk descends: [`push_dir`](Self::push_dir) before visiting a directory's
+/// children, [`truncate`](Self::truncate) back afterwards.
+#[derive(Default)]
+pub struct GitIgnore {
+    rules: Vec<Rule>,
+}
+
+impl GitIgnore {
+    /// Rules for `dir` (an absolute, canonical path): `.git/info/exclude` and
+    /// every `.gitignore` from the repository root down to `dir`. Outside a
+    /// repository only `dir`'s own `.gitignore` applies.
+    pub fn for_dir(dir: &Path) -> GitIgnore {
+        let mut ignore = GitIgnore::default();
+        let Some(root) = dir.ancestors().find(|d| d.join(".git").exists()) else {
+            ignore.push_dir(dir);
+            return ignore;
+        };
+
+        ignore.push_file(&root.join(".git/info/exclude"), root);
+        let mut chain: Vec<&Path> = dir.ancestors().take_while(|d| d.starts_with(root)).collect();
+        chain.reverse();
+        for d in chain {
+            ignore.push_dir(d);
+        }
+        ignore
+    }
+
+    /// Adds the rules from `dir/.gitignore`, if it has one.
+    pub fn push_dir(&mut self, dir: &Path) {
+        self.push_file(&dir.join(".gitignore"), dir);
+    }
+
+    fn push_file(&mut self, file: &Path, base: &Path) {
+        if let Ok(content) = fs::read_to_string(file) {
+            self.rules.extend(content.lines().filter_map(|l| Rule::parse(l, base)));
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    pub fn truncate(&mut self, len: usize) {
+        self.rules.truncate(len);
+    }
+
+    /// Like git, the last matching rule wins, so `!keep.log` can re-include
+    /// a file after `*.log`.
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        if path.file_name().is_some_and(|n| n == ".git") {
+            return true;
+        }
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| rule.matches(path, is_dir))
+            .is_some_and(|rule| !rule.negated)
+    }
+}
+
+/// Shell-style glob: `*` and `?` stay within one path component, `**`
+/// crosses directories, `[a-z]` / `[!a-z]` match character classes.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    glob_match_chars(&pattern, &text)
+}
+
+fn glob_match_chars(p: &[char], t: &[char]) -> bool {
+    match p.first() {
+        None => t.is_empty(),
+        Some('*') if p.get(1) == Some(&'*') => {
+            // `**/` also matches no directories at all, so `a/**/b` matches `a/b`.
+            let (rest, whole_components) = match p.get(2) {
+                Some('/') => (&p[3..], true),
+                _ => (&p[2..], false),
+            };
+            (0..=t.len())
+                .filter(|&i| !whole_components || i == 0 || t[i - 1] == '/')
+                .any(|i| glob_match_chars(rest, &t[i..]))
+        }
+        Some('*') => (0..=t.len())
+            .take_while(|&i| i == 0 || t[i - 1] != '/')
+            .any(|i| glob_match_chars(&p[1..], &t[i..])),
+        Some('?') => t.first().is_some_and(|&c| c != '/') && glob_match_chars(&p[1..], &t[1..]),
+        Some('[') => match (t.first(), class_end(p)) {
+            (Some(&c), Some(end)) => {
+                c != '/' && class_matches(&p[1..end], c) && glob_match_chars(&p[end + 1..], &t[1..])
+            }
+            // No closing `]`: a literal bracket
+            (Some(&c), None) => c == '[' && glob_match_chars(&p[1..], &t[1..]),
+            (None, _) => false,
+        },
+        Some(&c) => t.first() == Some(&c) && glob_match_chars(&p[1..], &t[1..]),
+    }
+}
+
+// Index of the `]` closing the class that starts at p[0]. A `]` right after
+// `[` or `[!` is part of the class.
+fn class_end(p: &[char]) -> Option<usize> {
+    let mut i = 1;
+    if matches!(p.get(i), Some('!') | Some('^')) {
+        i += 1;
+    }
+    if p.get(i) == Some(&']') {
+        i += 1;
+    }
+    p[i..].iter().position(|&c| c == ']').map(|pos| i + pos)
+}
+
+fn class_matches(class: &[char], c: char) -> bool {
+    let (negated, class) = match class.first() {
+        Some('!') | Some('^') => (true, &class[1..]),
+        _ => (false, class),
+    };
+    let mut found = false;
+    let mut i = 0;
+    while i < class.len() {
+        if class.get(i + 1) == Some(&'-') && i + 2 < class.len() {
+            found |= (class[i]..=class[i + 2]).contains(&c);
+            i += 3;
+        } else {
+            found |= class[i] == c;
+            i += 1;
+        }
+    }
+    found != negated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("*.rs", "main.rs"));
+        assert!(!glob_match("*.rs", "src/main.rs"));
+        assert!(glob_match("src/**/*.rs", "src/main.rs"));
+        assert!(glob_match("src/**/*.rs", "src/a/b/main.rs"));
+        assert!(!glob_match("a/**/b", "a/xb"));
+        assert!(glob_match("build/**", "build/out/x.o"));
+        assert!(glob_match("file?.[ch]", "file1.c"));
+        assert!(!glob_match("file?.[!ch]", "file1.c"));
+        assert!(glob_match("[a-c]*", "beta"));
+        assert!(glob_match("odd[", "odd["));
+    }
+
+    #[test]
+    fn test_rules() {
+        let root = PathBuf::from("/repo");
+        let mut ignore = GitIgnore::default();
+        for line in ["# comment", "*.log", "!keep.log", "target/", "/docs/build", "\\#notes"] {
+            ignore.rules.extend(Rule::parse(line, &root));
+        }
+
+        assert!(ignore.is_ignored(&root.join("a/debug.log"), false));
+        assert!(!ignore.is_ignored(&root.join("a/keep.log"), false));
+        assert!(ignore.is_ignored(&root.join("sub/target"), true));
+        assert!(!ignore.is_ignored(&root.join("sub/target"), false));
+        assert!(ignore.is_ignored(&root.join("docs/build"), true));
+        assert!(!ignore.is_ignored(&root.join("sub/docs/build"), true));
+        assert!(ignore.is_ignored(&root.join("#notes"), false));
+        assert!(ignore.is_ignored(&root.join(".git"), true));
+        assert!(!ignore.is_ignored(Path::new("/elsewhere/debug.log"), false));
+    }
+}
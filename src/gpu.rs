@@ -0,0 +1,371 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+/// One `/sys/class/drm/cardN`.
+#[derive(Debug, PartialEq)]
+pub struct Device {
+    pub card: String,
+    pub driver: String,
+    /// Bus address of the device (`0000:03:00.0`, or the platform device
+    /// name on SoCs); fdinfo's `drm-pdev` refers to it.
+    pub slot: String,
+    pub vendor: &'static str,
+    /// Utilization in percent, when the driver reports it (amdgpu).
+    pub busy: Option<f32>,
+    pub vram_used: Option<u64>,
+    pub vram_total: Option<u64>,
+}
+
+/// A DRM client: one open GPU context of a process, as fdinfo reports it.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Client {
+    pub pid: u32,
+    pub name: String,
+    pub driver: String,
+    pub pdev: Option<String>,
+    pub id: u64,
+    /// Busy time per engine in nanoseconds, cumulative
+    pub engines: HashMap<String, u64>,
+    /// Bytes of GPU memory
+    pub memory: u64,
+}
+
+/// GPU use of one process on one device over a sampling interval.
+#[derive(Debug, PartialEq)]
+pub struct ProcessUsage {
+    pub pid: u32,
+    pub name: String,
+    pub card: String,
+    /// Busiest engine, in percent of the interval
+    pub busy: f32,
+    pub memory: u64,
+}
+
+/// GPUs under `drm_root` (`/sys/class/drm`); empty when there are none.
+pub fn devices(drm_root: &Path) -> Vec<Device> {
+    let Ok(entries) = fs::read_dir(drm_root) else {
+        return Vec::new();
+    };
+    let mut devices: Vec<Device> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let card = entry.file_name().to_string_lossy().to_string();
+            // card0-HDMI-A-1 and friends are connectors, not devices.
+            let number = card.strip_prefix("card")?;
+            if number.is_empty() || !number.bytes().all(|b| b.is_ascii_digit()) {
+                return None;
+            }
+            read_device(card, &entry.path().join("device"))
+        })
+        .collect();
+    devices.sort_by_key(|d| (d.card.len(), d.card.clone()));
+    devices
+}
+
+fn read_device(card: String, device: &Path) -> Option<Device> {
+    let driver = fs::read_link(device.join("driver")).ok()?;
+    let driver = driver.file_name()?.to_string_lossy().to_string();
+    let slot = fs::canonicalize(device)
+        .ok()
+        .and_then(|p| p.file_name().map(|n| n.to_string_lossy().to_string()))
+        .unwrap_or_default();
+    let read = |name: &str| fs::read_to_string(device.join(name)).ok().map(|s| s.trim().to_string());
+    let vendor = match read("vendor").as_deref() {
+        Some("0x1002") => "AMD",
+        Some("0x8086") => "Intel",
+        Some("0x10de") => "NVIDIA",
+        Some("0x106b") => "Apple",
+        _ if driver == "asahi" => "Apple",
+        _ => "Unknown",
+    };
+    Some(Device {
+        busy: read("gpu_busy_percent").and_then(|s| s.parse().ok()),
+        vram_used: read("mem_info_vram_used").and_then(|s| s.parse().ok()),
+        vram_total: read("mem_info_vram_total").and_then(|s| s.parse().ok()),
+        card,
+        driver,
+        slot,
+        vendor,
+    })
+}
+
+/// Every DRM client visible under `proc_root` (`/proc`), from the
+/// `drm-*` keys of each process's fdinfo. Processes we may not inspect
+/// are skipped.
+pub fn clients(proc_root: &Path) -> Vec<Client> {
+    let Ok(entries) = fs::read_dir(proc_root) else {
+        return Vec::new();
+    };
+    let mut clients: Vec<Client> = Vec::new();
+    for entry in entries.flatten() {
+        let Ok(pid) = entry.file_name().to_string_lossy().parse::<u32>() else {
+            continue;
+        };
+        let Ok(fds) = fs::read_dir(entry.path().join("fd")) else {
+            continue;
+        };
+        let name = fs::read_to_string(entry.path().join("comm")).unwrap_or_default().trim().to_string();
+        for fd in fds.flatten() {
+            // Only GPU device nodes have DRM fdinfo; skip reading the rest.
+            let is_drm = fs::read_link(fd.path()).is_ok_and(|target| target.starts_with("/dev/dri"));
+            if !is_drm {
+                continue;
+            }
+            let fdinfo = entry.path().join("fdinfo").join(fd.file_name());
+            let Some(mut client) = fs::read_to_string(fdinfo).ok().and_then(|text| parse_fdinfo(&text)) else {
+                continue;
+            };
+            // Duplicated fds share a client; count it once.
+            let duplicate = clients
+                .iter()
+                .any(|c| c.pid == pid && c.id == client.id && c.driver == client.driver && c.pdev == client.pdev);
+            if !duplicate {
+                client.pid = pid;
+                client.name = name.clone();
+                clients.push(client);
+            }
+        }
+    }
+    clients
+}
+
+/// Parses the DRM keys of one fdinfo file, or `None` when it isn't a DRM fd.
+fn parse_fdinfo(text: &str) -> Option<Client> {
+    let mut client = Client::default();
+    let mut driver = None;
+    let mut legacy_memory = 0;
+    let mut total_memory = None;
+    for line in text.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        match key {
+            "drm-driver" => driver = Some(value.to_string()),
+            "drm-pdev" => client.pdev = Some(value.to_string()),
+            "drm-client-id" => client.id = value.parse().unwrap_or(0),
+            _ => {
+                if let Some(engine) = key.strip_prefix("drm-engine-") {
+                    // drm-engine-capacity-<engine> is a count, not a time.
+                    if !engine.starts_with("capacity-") {
+                        let ns = value.trim_end_matches("ns").trim().parse().unwrap_or(0);
+                        client.engines.insert(engine.to_string(), ns);
+                    }
+                } else if key.starts_with("drm-total-") {
+                    *total_memory.get_or_insert(0) += parse_size(value);
+                } else if key.starts_with("drm-memory-") {
+                    legacy_memory += parse_size(value);
+                }
+            }
+        }
+    }
+    client.driver = driver?;
+    // drm-memory-* predates drm-total-*; drivers may report both.
+    client.memory = total_memory.unwrap_or(legacy_memory);
+    Some(client)
+}
+
+/// fdinfo sizes: a number with an optional `KiB`/`MiB`/`GiB` unit.
+fn parse_size(value: &str) -> u64 {
+    let (number, unit) = value.split_once(' ').unwrap_or((value, ""));
+    let multiplier = match unit.trim() {
+        "KiB" => 1 << 10,
+        "MiB" => 1 << 20,
+        "GiB" => 1 << 30,
+        _ => 1,
+    };
+    number.parse::<u64>().unwrap_or(0) * multiplier
+}
+
+fn client_card<'a>(client: &Client, devices: &'a [Device]) -> Option<&'a Device> {
+    match &client.pdev {
+        Some(pdev) => devices.iter().find(|d| &d.slot == pdev),
+        // Without drm-pdev, only a driver with a single device is unambiguous.
+        None => {
+            let mut same_driver = devices.iter().filter(|d| d.driver == client.driver);
+            let device = same_driver.next();
+            if same_driver.next().is_some() {
+                None
+            } else {
+                device
+            }
+        }
+    }
+}
+
+/// Per-process use between two `clients()` samples taken `elapsed` apart,
+/// busiest first.
+pub fn process_usage(devices: &[Device], before: &[Client], after: &[Client], elapsed: Duration) -> Vec<ProcessUsage> {
+    // Per (pid, card): engine busy time over the interval, and the usage row.
+    let mut busy: HashMap<(u32, String), HashMap<String, u64>> = HashMap::new();
+    let mut rows: HashMap<(u32, String), ProcessUsage> = HashMap::new();
+    for client in after {
+        let card = client_card(client, devices).map(|d| d.card.clone()).unwrap_or_else(|| client.driver.clone());
+        let previous = before
+            .iter()
+            .find(|c| c.pid == client.pid && c.id == client.id && c.driver == client.driver && c.pdev == client.pdev);
+        let engines = busy.entry((client.pid, card.clone())).or_default();
+        for (engine, ns) in &client.engines {
+            let start = previous.and_then(|p| p.engines.get(engine)).copied().unwrap_or(*ns);
+            *engines.entry(engine.clone()).or_default() += ns.saturating_sub(start);
+        }
+        let row = rows.entry((client.pid, card.clone())).or_insert_with(|| ProcessUsage {
+            pid: client.pid,
+            name: client.name.clone(),
+            card,
+            busy: 0.0,
+            memory: 0,
+        });
+        row.memory += client.memory;
+    }
+
+    let elapsed = elapsed.as_nanos().max(1) as f64;
+    let mut result: Vec<ProcessUsage> = rows
+        .into_iter()
+        .map(|(key, mut row)| {
+            let busiest = busy.get(&key).and_then(|e| e.values().copied().max()).unwrap_or(0);
+            row.busy = (busiest as f64 / elapsed * 100.0).min(100.0) as f32;
+            row
+        })
+        .collect();
+    result.sort_by(|a, b| b.busy.total_cmp(&a.busy).then(b.memory.cmp(&a.memory)).then(a.pid.cmp(&b.pid)));
+    result
+}
+
+/// Utilization of `device` when the driver has no `gpu_busy_percent`:
+/// the busiest engine summed over every process using it.
+pub fn device_busy(device: &Device, usage: &[ProcessUsage]) -> Option<f32> {
+    if device.busy.is_some() {
+        return device.busy;
+    }
+    let mut users = usage.iter().filter(|u| u.card == device.card).peekable();
+    users.peek()?;
+    Some(users.map(|u| u.busy).sum::<f32>().min(100.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    const AMD_FDINFO: &str = "pos:\t0\nflags:\t02100002\nmnt_id:\t27\ndrm-driver:\tamdgpu\ndrm-pdev:\t0000:03:00.0\n\
+        drm-client-id:\t12\ndrm-memory-vram:\t2048 KiB\ndrm-memory-gtt:\t512 KiB\ndrm-engine-gfx:\t1000000000 ns\n\
+        drm-engine-compute:\t0 ns\n";
+    const I915_FDINFO: &str = "drm-driver:\ti915\ndrm-pdev:\t0000:00:02.0\ndrm-client-id:\t7\n\
+        drm-engine-render:\t500000000 ns\ndrm-engine-capacity-video:\t2\ndrm-total-system0:\t8 MiB\n\
+        drm-resident-system0:\t4 MiB\ndrm-memory-system0:\t99 MiB\n";
+
+    struct Fixture(PathBuf);
+
+    impl Fixture {
+        fn new(name: &str) -> Fixture {
+            let dir = std::env::temp_dir().join(format!("aeroshell_gpu_{}_{}", name, std::process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+            Fixture(dir)
+        }
+
+        fn write(&self, path: &str, contents: &str) {
+            let path = self.0.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+
+        #[cfg(unix)]
+        fn link(&self, path: &str, target: &str) {
+            let path = self.0.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::os::unix::fs::symlink(target, path).unwrap();
+        }
+    }
+
+    impl Drop for Fixture {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn test_parse_fdinfo() {
+        let amd = parse_fdinfo(AMD_FDINFO).unwrap();
+        assert_eq!(amd.driver, "amdgpu");
+        assert_eq!(amd.pdev.as_deref(), Some("0000:03:00.0"));
+        assert_eq!(amd.id, 12);
+        assert_eq!(amd.memory, 2560 * 1024);
+        assert_eq!(amd.engines.get("gfx"), Some(&1_000_000_000));
+
+        // drm-total-* wins over the legacy key; capacities aren't engines.
+        let intel = parse_fdinfo(I915_FDINFO).unwrap();
+        assert_eq!(intel.memory, 8 << 20);
+        assert_eq!(intel.engines.len(), 1);
+
+        assert!(parse_fdinfo("pos:\t0\nflags:\t02\n").is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_devices_from_sysfs() {
+        let sys = Fixture::new("sysfs");
+        sys.write("devices/0000:03:00.0/vendor", "0x1002\n");
+        sys.write("devices/0000:03:00.0/gpu_busy_percent", "37\n");
+        sys.write("devices/0000:03:00.0/mem_info_vram_used", "1073741824\n");
+        sys.write("devices/0000:03:00.0/mem_info_vram_total", "8589934592\n");
+        sys.link("devices/0000:03:00.0/driver", "../../drivers/amdgpu");
+        sys.write("devices/206400000.gpu/uevent", "");
+        sys.link("devices/206400000.gpu/driver", "../../drivers/asahi");
+        let devices_dir = sys.0.join("devices");
+        sys.link("drm/card1/device", devices_dir.join("0000:03:00.0").to_str().unwrap());
+        sys.link("drm/card0/device", devices_dir.join("206400000.gpu").to_str().unwrap());
+        sys.write("drm/card1-DP-1/status", "connected\n");
+        sys.write("drm/renderD128/dev", "226:128\n");
+
+        let devices = devices(&sys.0.join("drm"));
+        assert_eq!(devices.len(), 2);
+        assert_eq!((devices[0].card.as_str(), devices[0].driver.as_str(), devices[0].vendor), ("card0", "asahi", "Apple"));
+        assert_eq!(devices[0].busy, None);
+        let amd = &devices[1];
+        assert_eq!((amd.slot.as_str(), amd.vendor, amd.busy), ("0000:03:00.0", "AMD", Some(37.0)));
+        assert_eq!((amd.vram_used, amd.vram_total), (Some(1 << 30), Some(8 << 30)));
+
+        assert!(super::devices(&sys.0.join("missing")).is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_process_usage() {
+        let proc = Fixture::new("proc");
+        proc.write("100/comm", "firefox\n");
+        proc.link("100/fd/3", "/dev/dri/renderD128");
+        proc.write("100/fdinfo/3", I915_FDINFO);
+        // A dup of fd 3 is the same client.
+        proc.link("100/fd/4", "/dev/dri/renderD128");
+        proc.write("100/fdinfo/4", I915_FDINFO);
+        proc.link("100/fd/5", "/home/me/notes.txt");
+        proc.write("100/fdinfo/5", "pos:\t0\n");
+        proc.write("self/comm", "ignored\n");
+
+        let before = clients(&proc.0);
+        assert_eq!(before.len(), 1);
+        assert_eq!((before[0].pid, before[0].name.as_str()), (100, "firefox"));
+
+        let mut after = before.clone();
+        after[0].engines.insert("render".to_string(), 750_000_000);
+        let devices = vec![Device {
+            card: "card0".to_string(),
+            driver: "i915".to_string(),
+            slot: "0000:00:02.0".to_string(),
+            vendor: "Intel",
+            busy: None,
+            vram_used: None,
+            vram_total: None,
+        }];
+        let usage = process_usage(&devices, &before, &after, Duration::from_millis(500));
+        assert_eq!(usage.len(), 1);
+        assert_eq!(usage[0].card, "card0");
+        assert!((usage[0].busy - 50.0).abs() < 0.01);
+        assert_eq!(usage[0].memory, 8 << 20);
+        assert_eq!(device_busy(&devices[0], &usage), Some(usage[0].busy));
+    }
+}
@@ -0,0 +1,797 @@
+use std::fs::{self, Metadata};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+#[cfg(unix)]
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+use chrono::{DateTime, Local};
+use serde::Serialize;
+
+use crate::config::RootConfig;
+use crate::file_class::{self, FileClass, Kind};
+use crate::git_status::RepoStatus;
+use crate::gitignore::GitIgnore;
+use crate::output::{self, Format};
+use crate::terminal;
+use crate::theme::{self, Style};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SortBy {
+    Name,
+    Time,
+    Size,
+}
+
+#[derive(Debug, PartialEq)]
+struct LsOptions {
+    /// -a: everything, including `.` and `..`
+    all: bool,
+    /// -A: dotfiles, but not `.` and `..`
+    almost_all: bool,
+    /// -l
+    long: bool,
+    /// -h
+    human: bool,
+    sort: SortBy,
+    /// -r
+    reverse: bool,
+    /// -d: list directories themselves, not their contents
+    directory: bool,
+    /// --tree, optionally limited by --depth N
+    tree: bool,
+    depth: Option<usize>,
+    /// --git-ignore: skip paths matched by .gitignore
+    git_ignore: bool,
+    /// Git status column, on unless --no-git
+    git: bool,
+    /// --icons, or `[config] icons`
+    icons: bool,
+    /// --json / --tsv
+    format: Format,
+    paths: Vec<String>,
+}
+
+impl Default for LsOptions {
+    fn default() -> Self {
+        Self {
+            all: false,
+            almost_all: false,
+            long: false,
+            human: false,
+            sort: SortBy::Name,
+            reverse: false,
+            directory: false,
+            tree: false,
+            depth: None,
+            git_ignore: false,
+            git: true,
+            icons: false,
+            format: Format::Table,
+            paths: Vec::new(),
+        }
+    }
+}
+
+fn parse_args(args: &[&str]) -> Result<LsOptions, String> {
+    let mut opts = LsOptions::default();
+    let mut only_paths = false;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if only_paths || !arg.starts_with('-') || *arg == "-" {
+            opts.paths.push(arg.to_string());
+            continue;
+        }
+        match *arg {
+            "--" => only_paths = true,
+            "--tree" => opts.tree = true,
+            "--git-ignore" => opts.git_ignore = true,
+            "--no-git" => opts.git = false,
+            "--icons" => opts.icons = true,
+            "--json" | "--tsv" => opts.format = Format::from_flag(arg).unwrap_or_default(),
+            "--depth" | "-L" => {
+                let value = args.next().ok_or("--depth needs a number")?;
+                opts.depth = Some(parse_depth(value)?);
+            }
+            depth if depth.starts_with("--depth=") => {
+                opts.depth = Some(parse_depth(&depth["--depth=".len()..])?);
+            }
+            "--all" => opts.all = true,
+            "--almost-all" => opts.almost_all = true,
+            "--human-readable" => opts.human = true,
+            "--reverse" => opts.reverse = true,
+            "--directory" => opts.directory = true,
+            long if long.starts_with("--") => return Err(format!("unknown option '{}'", long)),
+            short => {
+                for flag in short.chars().skip(1) {
+                    match flag {
+                        'a' => opts.all = true,
+                        'A' => opts.almost_all = true,
+                        'l' => opts.long = true,
+                        'h' => opts.human = true,
+                        't' => opts.sort = SortBy::Time,
+                        'S' => opts.sort = SortBy::Size,
+                        'r' => opts.reverse = true,
+                        'd' => opts.directory = true,
+                        other => return Err(format!("unknown option '-{}'", other)),
+                    }
+                }
+            }
+        }
+    }
+
+    if opts.depth.is_some() {
+        opts.tree = true;
+    }
+    if opts.paths.is_empty() {
+        opts.paths.push(".".to_string());
+    }
+    Ok(opts)
+}
+
+fn parse_depth(value: &str) -> Result<usize, String> {
+    value.parse().map_err(|_| format!("invalid depth '{}'", value))
+}
+
+/// One thing to print: a directory entry or a path named on the command line.
+struct Entry {
+    name: String,
+    path: PathBuf,
+    /// lstat() data, so symlinks describe themselves.
+    metadata: Option<Metadata>,
+}
+
+impl Entry {
+    fn new(name: String, path: PathBuf) -> Self {
+        let metadata = fs::symlink_metadata(&path).ok();
+        Self { name, path, metadata }
+    }
+
+    /// A path named on the command line, which has to exist.
+    fn from_arg(arg: &str) -> std::io::Result<Self> {
+        let metadata = fs::symlink_metadata(arg)?;
+        Ok(Self { name: arg.to_string(), path: PathBuf::from(arg), metadata: Some(metadata) })
+    }
+
+    fn is_dir(&self) -> bool {
+        self.path.is_dir()
+    }
+
+    fn size(&self) -> u64 {
+        self.metadata.as_ref().map(|m| m.len()).unwrap_or(0)
+    }
+
+    fn modified(&self) -> SystemTime {
+        self.metadata
+            .as_ref()
+            .and_then(|m| m.modified().ok())
+            .unwrap_or(SystemTime::UNIX_EPOCH)
+    }
+}
+
+pub fn cmd_ls(args: &[&str], config: &RootConfig) {
+    let mut opts = match parse_args(args) {
+        Ok(opts) => opts,
+        Err(e) => {
+            eprintln!("ls: {}", e);
+            eprintln!("Usage: ls [-aAlhtSrd] [--tree [--depth N] [--git-ignore]] [--no-git] [--icons] [--json|--tsv] [path...]");
+            return;
+        }
+    };
+
+    opts.icons |= config.config.icons;
+    let globs = file_class::glob_keys(&config.theme.files);
+
+    if opts.tree {
+        if opts.format != Format::Table {
+            eprintln!("ls: --json and --tsv can't be combined with --tree");
+            return;
+        }
+        print_trees(&opts, &globs, config);
+        return;
+    }
+
+    // Like coreutils: plain files first, then one section per directory.
+    let mut files = Vec::new();
+    let mut dirs = Vec::new();
+    for path in &opts.paths {
+        let entry = match Entry::from_arg(path) {
+            Ok(entry) => entry,
+            Err(e) => {
+                eprintln!("ls: cannot access '{}': {}", path, e);
+                continue;
+            }
+        };
+        if entry.is_dir() && !opts.directory {
+            dirs.push(entry);
+        } else {
+            files.push(entry);
+        }
+    }
+
+    if opts.format != Format::Table {
+        print_machine(files, dirs, &opts);
+        return;
+    }
+
+    let show_headers = opts.paths.len() > 1;
+    let mut first = true;
+
+    if !files.is_empty() {
+        sort_entries(&mut files, &opts);
+        let parent = files[0].path.parent().filter(|p| !p.as_os_str().is_empty());
+        let git = load_git(parent.unwrap_or(Path::new(".")), &opts);
+        print_entries(&files, &opts, &globs, git.as_ref(), config);
+        first = false;
+    }
+
+    sort_entries(&mut dirs, &opts);
+    for dir in dirs {
+        if !first {
+            println!();
+        }
+        first = false;
+        if show_headers {
+            println!("{}:", dir.name);
+        }
+        match read_entries(&dir.path, &opts) {
+            Ok(mut entries) => {
+                sort_entries(&mut entries, &opts);
+                let git = load_git(&dir.path, &opts);
+                print_entries(&entries, &opts, &globs, git.as_ref(), config);
+            },
+            Err(e) => eprintln!("ls: {}: {}", dir.name, e),
+        }
+    }
+}
+
+/// One entry as `ls --json` and `ls --tsv` report it.
+#[derive(Serialize)]
+struct EntryInfo {
+    name: String,
+    path: String,
+    #[serde(rename = "type")]
+    kind: &'static str,
+    size: u64,
+    mode: String,
+    owner: String,
+    group: String,
+    /// RFC 3339
+    modified: Option<String>,
+    /// Where a symlink points
+    target: Option<String>,
+    git: Option<&'static str>,
+}
+
+impl EntryInfo {
+    fn new(entry: &Entry, git: Option<&RepoStatus>) -> EntryInfo {
+        let class = FileClass::new(&entry.name, &entry.path, entry.metadata.as_ref());
+        let kind = match class.kind {
+            _ if class.symlink => "symlink",
+            Kind::File => "file",
+            Kind::Directory => "directory",
+            Kind::Fifo => "fifo",
+            Kind::Socket => "socket",
+            Kind::BlockDevice => "block_device",
+            Kind::CharDevice => "char_device",
+        };
+        let (mode, _, owner, group) = match &entry.metadata {
+            Some(m) => ownership_columns(m),
+            None => ("?".repeat(10), String::new(), String::new(), String::new()),
+        };
+        let modified = entry.metadata.as_ref().and_then(|m| m.modified().ok());
+        EntryInfo {
+            name: entry.name.clone(),
+            path: entry.path.to_string_lossy().to_string(),
+            kind,
+            size: entry.size(),
+            mode,
+            owner,
+            group,
+            modified: modified.map(|t| DateTime::<Local>::from(t).to_rfc3339()),
+            target: class.symlink.then(|| fs::read_link(&entry.path).ok()).flatten().map(|t| t.to_string_lossy().to_string()),
+            git: git.and_then(|g| g.status(&entry.path, entry.is_dir())).map(|s| s.label()),
+        }
+    }
+}
+
+/// `--json` / `--tsv`: one flat list of everything that would be printed.
+fn print_machine(mut files: Vec<Entry>, mut dirs: Vec<Entry>, opts: &LsOptions) {
+    let mut listed = Vec::new();
+    if !files.is_empty() {
+        sort_entries(&mut files, opts);
+        let parent = files[0].path.parent().filter(|p| !p.as_os_str().is_empty());
+        let git = load_git(parent.unwrap_or(Path::new(".")), opts);
+        listed.extend(files.iter().map(|e| EntryInfo::new(e, git.as_ref())));
+    }
+    sort_entries(&mut dirs, opts);
+    for dir in dirs {
+        match read_entries(&dir.path, opts) {
+            Ok(mut entries) => {
+                sort_entries(&mut entries, opts);
+                let git = load_git(&dir.path, opts);
+                listed.extend(entries.iter().map(|e| EntryInfo::new(e, git.as_ref())));
+            },
+            Err(e) => eprintln!("ls: {}: {}", dir.name, e),
+        }
+    }
+
+    if opts.format == Format::Json {
+        output::print_json(&listed);
+        return;
+    }
+    output::print_tsv(
+        &["name", "path", "type", "size", "mode", "owner", "group", "modified", "target", "git"],
+        listed.into_iter().map(|e| {
+            vec![
+                e.name,
+                e.path,
+                e.kind.to_string(),
+                e.size.to_string(),
+                e.mode,
+                e.owner,
+                e.group,
+                e.modified.unwrap_or_default(),
+                e.target.unwrap_or_default(),
+                e.git.unwrap_or_default().to_string(),
+            ]
+        }),
+    );
+}
+
+fn load_git(dir: &Path, opts: &LsOptions) -> Option<RepoStatus> {
+    if opts.git { RepoStatus::load(dir) } else { None }
+}
+
+fn read_entries(dir: &Path, opts: &LsOptions) -> std::io::Result<Vec<Entry>> {
+    let mut entries = Vec::new();
+    // Scripts want the directory's contents, not its links to itself and its parent.
+    if opts.all && !opts.tree && opts.format == Format::Table {
+        entries.push(Entry::new(".".to_string(), dir.to_path_buf()));
+        entries.push(Entry::new("..".to_string(), dir.join("..")));
+    }
+    for entry in fs::read_dir(dir)?.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name.starts_with('.') && !(opts.all || opts.almost_all) {
+            continue;
+        }
+        entries.push(Entry::new(name, entry.path()));
+    }
+    Ok(entries)
+}
+
+fn sort_entries(entries: &mut [Entry], opts: &LsOptions) {
+    match opts.sort {
+        SortBy::Name => entries.sort_by(|a, b| a.name.cmp(&b.name)),
+        // Newest / largest first, ties broken by name
+        SortBy::Time => entries.sort_by(|a, b| b.modified().cmp(&a.modified()).then(a.name.cmp(&b.name))),
+        SortBy::Size => entries.sort_by(|a, b| b.size().cmp(&a.size()).then(a.name.cmp(&b.name))),
+    }
+    if opts.reverse {
+        entries.reverse();
+    }
+}
+
+/// `globs` is `file_class::glob_keys` of the theme, worked out once per listing.
+fn print_entries(entries: &[Entry], opts: &LsOptions, globs: &[&str], git: Option<&RepoStatus>, config: &RootConfig) {
+    if opts.long {
+        print_long(entries, opts, globs, git, config);
+        return;
+    }
+    let names: Vec<String> = entries
+        .iter()
+        .map(|e| format!("{}{}", git_marker(e, git, config), colored_name(e, opts, globs, config)))
+        .collect();
+
+    // Piped output gets one name per line so `ls | grep` etc. work.
+    let Some(term_width) = terminal::stdout_width() else {
+        for name in &names {
+            println!("{}", name);
+        }
+        return;
+    };
+
+    let widths: Vec<usize> = names.iter().map(|n| terminal::display_width(n)).collect();
+    let column_widths = grid_columns(&widths, term_width);
+    let rows = names.len().div_ceil(column_widths.len().max(1));
+
+    for row in 0..rows {
+        let mut line = String::new();
+        for (col, col_width) in column_widths.iter().enumerate() {
+            let i = col * rows + row;
+            let Some(name) = names.get(i) else { break };
+            line.push_str(name);
+            // No trailing padding after the last name on a line
+            if names.get(i + rows).is_some() && col + 1 < column_widths.len() {
+                line.push_str(&" ".repeat(col_width - widths[i]));
+            }
+        }
+        println!("{}", line);
+    }
+}
+
+#[derive(Default)]
+struct TreeTotals {
+    dirs: usize,
+    files: usize,
+    bytes: u64,
+}
+
+/// State carried down one `ls --tree` walk.
+struct TreeWalk<'a> {
+    opts: &'a LsOptions,
+    globs: &'a [&'a str],
+    config: &'a RootConfig,
+    guide: Style,
+    ignore: GitIgnore,
+    git: Option<RepoStatus>,
+    totals: TreeTotals,
+}
+
+fn print_trees(opts: &LsOptions, globs: &[&str], config: &RootConfig) {
+    let mut walk = TreeWalk {
+        opts,
+        globs,
+        config,
+        guide: theme::resolve(&config.theme.autocomplete, config),
+        ignore: GitIgnore::default(),
+        git: None,
+        totals: TreeTotals::default(),
+    };
+
+    for (i, path) in opts.paths.iter().enumerate() {
+        let root = match Entry::from_arg(path) {
+            Ok(root) => root,
+            Err(e) => {
+                eprintln!("ls: cannot access '{}': {}", path, e);
+                continue;
+            }
+        };
+        if i > 0 {
+            println!();
+        }
+        if !root.is_dir() {
+            println!("{}", colored_name(&root, opts, globs, config));
+            walk.totals.files += 1;
+            walk.totals.bytes += root.size();
+            continue;
+        }
+
+        // Ignore rules match against absolute paths, walked alongside the
+        // path as the user typed it.
+        let real = fs::canonicalize(&root.path).unwrap_or_else(|_| root.path.clone());
+        walk.ignore = if opts.git_ignore { GitIgnore::for_dir(&real) } else { GitIgnore::default() };
+        walk.git = load_git(&root.path, opts);
+
+        let children = walk.children(&root.path, &real);
+        println!(
+            "{}{}{}",
+            git_marker(&root, walk.git.as_ref(), config),
+            colored_name(&root, opts, globs, config),
+            walk.guide.paint(&format!(" {}", count_label(&children)))
+        );
+        walk.print_level(&children, &real, "", 1);
+    }
+
+    let totals = &walk.totals;
+    println!(
+        "\n{}",
+        walk.guide.paint(&format!(
+            "{} {}, {} {}, {}",
+            totals.dirs,
+            if totals.dirs == 1 { "directory" } else { "directories" },
+            totals.files,
+            if totals.files == 1 { "file" } else { "files" },
+            if totals.bytes < 1024 { format!("{} bytes", totals.bytes) } else { human_size(totals.bytes) }
+        ))
+    );
+}
+
+impl TreeWalk<'_> {
+    // A directory's listable children after the hidden-file and .gitignore
+    // filters, sorted like a normal listing.
+    fn children(&self, dir: &Path, real: &Path) -> Vec<Entry> {
+        let mut children: Vec<Entry> = read_entries(dir, self.opts)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|e| !self.opts.git_ignore || !self.ignore.is_ignored(&real.join(&e.name), e.is_dir()))
+            .collect();
+        sort_entries(&mut children, self.opts);
+        children
+    }
+
+    fn print_level(&mut self, children: &[Entry], real: &Path, prefix: &str, depth: usize) {
+        for (i, entry) in children.iter().enumerate() {
+            let last = i + 1 == children.len();
+            let branch = if last { "└── " } else { "├── " };
+            let guides = self.guide.paint(&format!("{}{}", prefix, branch));
+            let name = format!("{}{}", git_marker(entry, self.git.as_ref(), self.config), colored_name(entry, self.opts, self.globs, self.config));
+
+            // Symlinked directories are shown but not followed, so loops can't recurse forever.
+            let is_real_dir = entry.metadata.as_ref().is_some_and(|m| m.is_dir());
+            if !is_real_dir {
+                if entry.is_dir() {
+                    self.totals.dirs += 1;
+                } else {
+                    self.totals.files += 1;
+                    self.totals.bytes += entry.size();
+                }
+                println!("{}{}", guides, name);
+                continue;
+            }
+
+            self.totals.dirs += 1;
+            let child_real = real.join(&entry.name);
+            let rules = self.ignore.len();
+            if self.opts.git_ignore {
+                self.ignore.push_dir(&child_real);
+            }
+
+            let grandchildren = self.children(&entry.path, &child_real);
+            println!("{}{}{}", guides, name, self.guide.paint(&format!(" {}", count_label(&grandchildren))));
+
+            if self.opts.depth.is_none_or(|max| depth < max) {
+                let child_prefix = format!("{}{}", prefix, if last { "    " } else { "│   " });
+                self.print_level(&grandchildren, &child_real, &child_prefix, depth + 1);
+            }
+            self.ignore.truncate(rules);
+        }
+    }
+}
+
+fn count_label(children: &[Entry]) -> String {
+    let dirs = children.iter().filter(|e| e.is_dir()).count();
+    let files = children.len() - dirs;
+    match (dirs, files) {
+        (0, 0) => "(empty)".to_string(),
+        (0, f) => format!("({} {})", f, if f == 1 { "file" } else { "files" }),
+        (d, 0) => format!("({} {})", d, if d == 1 { "dir" } else { "dirs" }),
+        (d, f) => format!("({} {}, {} {})", d, if d == 1 { "dir" } else { "dirs" }, f, if f == 1 { "file" } else { "files" }),
+    }
+}
+
+const COLUMN_GAP: usize = 2;
+
+/// Picks the most columns that fit in `term_width`, filling top to bottom
+/// then left to right like `ls -C`. Returns each column's width, gap
+/// included.
+fn grid_columns(widths: &[usize], term_width: usize) -> Vec<usize> {
+    for cols in (1..=widths.len()).rev() {
+        let rows = widths.len().div_ceil(cols);
+        // Skip counts that would leave an empty column
+        if (cols - 1) * rows >= widths.len() {
+            continue;
+        }
+        let column_widths: Vec<usize> = widths
+            .chunks(rows)
+            .map(|column| column.iter().max().unwrap_or(&0) + COLUMN_GAP)
+            .collect();
+        if column_widths.iter().sum::<usize>() - COLUMN_GAP <= term_width {
+            return column_widths;
+        }
+    }
+    vec![widths.iter().max().copied().unwrap_or(0) + COLUMN_GAP]
+}
+
+fn print_long(entries: &[Entry], opts: &LsOptions, globs: &[&str], git: Option<&RepoStatus>, config: &RootConfig) {
+    struct Row {
+        mode: String,
+        links: String,
+        owner: String,
+        group: String,
+        size: String,
+        modified: String,
+        name: String,
+    }
+
+    let rows: Vec<Row> = entries
+        .iter()
+        .map(|entry| {
+            let (mode, links, owner, group) = match &entry.metadata {
+                Some(m) => ownership_columns(m),
+                None => ("?".repeat(10), "?".into(), "?".into(), "?".into()),
+            };
+            let size = if opts.human { human_size(entry.size()) } else { entry.size().to_string() };
+
+            let mut name = format!("{}{}", git_marker(entry, git, config), colored_name(entry, opts, globs, config));
+            let is_link = entry.metadata.as_ref().is_some_and(|m| m.file_type().is_symlink());
+            if is_link {
+                if let Ok(target) = fs::read_link(&entry.path) {
+                    name.push_str(&format!(" -> {}", target.display()));
+                }
+            }
+
+            Row { mode, links, owner, group, size, modified: format_time(entry.modified()), name }
+        })
+        .collect();
+
+    let width = |f: fn(&Row) -> &String| rows.iter().map(|r| f(r).chars().count()).max().unwrap_or(0);
+    let (lw, ow, gw, sw) = (width(|r| &r.links), width(|r| &r.owner), width(|r| &r.group), width(|r| &r.size));
+
+    for r in &rows {
+        println!("{} {:>lw$} {:<ow$} {:<gw$} {:>sw$} {} {}",
+            r.mode, r.links, r.owner, r.group, r.size, r.modified, r.name);
+    }
+}
+
+#[cfg(unix)]
+fn ownership_columns(m: &Metadata) -> (String, String, String, String) {
+    let owner = crate::context::user_name_for_uid(m.uid()).unwrap_or_else(|| m.uid().to_string());
+    let group = crate::context::group_name_for_gid(m.gid()).unwrap_or_else(|| m.gid().to_string());
+    (format_mode(m.permissions().mode()), m.nlink().to_string(), owner, group)
+}
+
+#[cfg(not(unix))]
+fn ownership_columns(m: &Metadata) -> (String, String, String, String) {
+    let mode = if m.is_dir() { "d" } else { "-" }.to_string()
+        + if m.permissions().readonly() { "r--r--r--" } else { "rw-rw-rw-" };
+    (mode, "1".into(), "-".into(), "-".into())
+}
+
+/// `drwxr-xr-x` style permission string from a raw `st_mode`.
+fn format_mode(mode: u32) -> String {
+    let kind = match mode & 0o170000 {
+        0o040000 => 'd',
+        0o120000 => 'l',
+        0o010000 => 'p',
+        0o140000 => 's',
+        0o060000 => 'b',
+        0o020000 => 'c',
+        _ => '-',
+    };
+    let mut out = String::with_capacity(10);
+    out.push(kind);
+
+    // (read, write, exec, special bit, char when special+exec, char when special only)
+    let triads = [
+        (0o400, 0o200, 0o100, 0o4000, 's', 'S'),
+        (0o040, 0o020, 0o010, 0o2000, 's', 'S'),
+        (0o004, 0o002, 0o001, 0o1000, 't', 'T'),
+    ];
+    for (r, w, x, special, with_x, without_x) in triads {
+        out.push(if mode & r != 0 { 'r' } else { '-' });
+        out.push(if mode & w != 0 { 'w' } else { '-' });
+        out.push(match (mode & x != 0, mode & special != 0) {
+            (true, true) => with_x,
+            (false, true) => without_x,
+            (true, false) => 'x',
+            (false, false) => '-',
+        });
+    }
+    out
+}
+
+/// Sizes like `ls -h`: `512`, `1.5K`, `23M`, `4.0G`.
+pub fn human_size(bytes: u64) -> String {
+    const UNITS: [&str; 6] = ["K", "M", "G", "T", "P", "E"];
+    if bytes < 1024 {
+        return bytes.to_string();
+    }
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    value /= 1024.0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if value < 10.0 {
+        format!("{:.1}{}", value, UNITS[unit])
+    } else {
+        format!("{:.0}{}", value, UNITS[unit])
+    }
+}
+
+// Recent files show the time, older ones (or future ones) the year.
+fn format_time(time: SystemTime) -> String {
+    let dt: DateTime<Local> = time.into();
+    let age = Local::now().signed_duration_since(dt);
+    if age.num_days() > 182 || age.num_seconds() < 0 {
+        dt.format("%b %e  %Y").to_string()
+    } else {
+        dt.format("%b %e %H:%M").to_string()
+    }
+}
+
+// The git status column: a colored marker and a space, blank for clean
+// entries, and nothing at all outside a repository.
+fn git_marker(entry: &Entry, git: Option<&RepoStatus>, config: &RootConfig) -> String {
+    let Some(git) = git else {
+        return String::new();
+    };
+    match git.status(&entry.path, entry.is_dir()) {
+        Some(status) => {
+            let style = theme::resolve(status.color(&config.theme.git), config);
+            format!("{} ", style.paint(&status.marker().to_string()))
+        }
+        None => "  ".to_string(),
+    }
+}
+
+fn colored_name(entry: &Entry, opts: &LsOptions, globs: &[&str], config: &RootConfig) -> String {
+    let files = &config.theme.files;
+    let class = FileClass::new(&entry.name, &entry.path, entry.metadata.as_ref());
+    let keys = class.keys(globs);
+
+    let color_name = keys.iter().find_map(|k| files.get(k)).map(|s| s.as_str()).unwrap_or("white");
+    let style = theme::resolve(color_name, config);
+    if opts.icons {
+        style.paint(&format!("{} {}", file_class::icon(&keys, &config.theme.icons), entry.name))
+    } else {
+        style.paint(&entry.name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_args() {
+        let opts = parse_args(&["-lah", "src", "--", "-weird"]).unwrap();
+        assert!(opts.long && opts.all && opts.human);
+        assert_eq!(opts.paths, vec!["src", "-weird"]);
+
+        let opts = parse_args(&["-tr"]).unwrap();
+        assert_eq!(opts.sort, SortBy::Time);
+        assert!(opts.reverse);
+        assert_eq!(opts.paths, vec!["."]);
+
+        assert!(parse_args(&["-z"]).is_err());
+
+        let opts = parse_args(&["--depth", "2", "--git-ignore"]).unwrap();
+        assert!(opts.tree && opts.git_ignore);
+        assert_eq!(opts.depth, Some(2));
+        assert_eq!(parse_args(&["--tree", "--depth=1"]).unwrap().depth, Some(1));
+        assert!(parse_args(&["--depth", "x"]).is_err());
+        assert!(parse_args(&["--depth"]).is_err());
+        assert_eq!(parse_args(&["-l", "--json"]).unwrap().format, Format::Json);
+    }
+
+    #[test]
+    fn test_machine_output_skips_dot_entries() {
+        let dir = std::env::temp_dir().join(format!("aeroshell-ls-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join(".hidden"), "").unwrap();
+        let names = |args: &[&str]| -> Vec<String> {
+            let mut names: Vec<String> =
+                read_entries(&dir, &parse_args(args).unwrap()).unwrap().into_iter().map(|e| e.name).collect();
+            names.sort();
+            names
+        };
+
+        assert_eq!(names(&["-a"]), vec![".", "..", ".hidden"]);
+        assert_eq!(names(&["-a", "--json"]), vec![".hidden"]);
+        assert_eq!(names(&["-a", "--tsv"]), vec![".hidden"]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_format_mode() {
+        assert_eq!(format_mode(0o040755), "drwxr-xr-x");
+        assert_eq!(format_mode(0o100644), "-rw-r--r--");
+        assert_eq!(format_mode(0o104755), "-rwsr-xr-x");
+        assert_eq!(format_mode(0o041777), "drwxrwxrwt");
+        assert_eq!(format_mode(0o120777), "lrwxrwxrwx");
+        assert_eq!(format_mode(0o102644), "-rw-r-Sr--");
+    }
+
+    #[test]
+    fn test_grid_columns() {
+        // Everything fits on one line
+        assert_eq!(grid_columns(&[3, 5, 4], 80), vec![5, 7, 6]);
+        // 6 names of width 10 in 40 columns: 3 columns of 2 rows
+        assert_eq!(grid_columns(&[10; 6], 40), vec![12, 12, 12]);
+        // Wider than the terminal: one per line
+        assert_eq!(grid_columns(&[100, 3], 40), vec![102]);
+        assert!(grid_columns(&[], 80).len() <= 1);
+    }
+
+    #[test]
+    fn test_human_size() {
+        assert_eq!(human_size(512), "512");
+        assert_eq!(human_size(1536), "1.5K");
+        assert_eq!(human_size(23 * 1024 * 1024), "23M");
+        assert_eq!(human_size(4 * 1024 * 1024 * 1024), "4.0G");
+    }
+}
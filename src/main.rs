@@ -2,268 +2,315 @@ mod config;
 mod prompt;
 mod completer;
 mod version;
+mod segments;
+mod context;
+mod theme;
+mod terminal;
+mod theme_export;
+mod ls;
+mod gitignore;
+mod git_status;
+mod file_class;
+mod gpu;
+mod proc;
+mod proc_top;
+mod proc_tree;
+mod proc_system;
+mod proc_info;
+mod output;
+mod config_check;
+mod config_migrate;
 
 use std::process::{Command, Stdio};
 use std::env;
 use std::fs;
 use std::path::PathBuf;
-use std::io::Read;
-#[cfg(unix)]
-use std::os::unix::fs::PermissionsExt;
+use std::io::{Read, Write};
+use std::time::{Duration, Instant};
 
-use sysinfo::{System, ProcessesToUpdate};
 
-use crate::config::{load_config, get_config_path, RootConfig};
-use crate::prompt::format_prompt;
+use crate::config::{load_config, get_config_path, get_themes_dir, set_config_value, RootConfig};
+use crate::prompt::{format_duration, format_prompt, AeroPrompt, PromptState};
+use crate::segments::SegmentCache;
 use crate::completer::AeroCompleter;
 
 use reedline::{
     Reedline, Signal, DefaultHinter,
-    FileBackedHistory, Color
+    FileBackedHistory
 };
 
-fn hex_to_rgb(hex: &str) -> Option<(u8, u8, u8)> {
-    let hex = hex.trim_start_matches('#');
-    if hex.len() == 6 {
-        let r = u8::from_str_radix(&hex[0..2], 16).ok()?;
-        let g = u8::from_str_radix(&hex[2..4], 16).ok()?;
-        let b = u8::from_str_radix(&hex[4..6], 16).ok()?;
-        Some((r, g, b))
+// Ask the terminal to raise a desktop notification for a finished long command.
+// iTerm2/WezTerm/Windows Terminal understand OSC 9; most others (foot, urxvt,
+// kitty, ghostty, VTE) understand OSC 777. Terminals decide themselves whether
+// to show it, which in practice means only while the window is unfocused.
+fn notify_command_finished(command: &str, elapsed: Duration) {
+    let body = format!("{} finished in {}", command, format_duration(elapsed));
+    let osc9 = matches!(
+        env::var("TERM_PROGRAM").as_deref(),
+        Ok("iTerm.app") | Ok("WezTerm")
+    ) || env::var("WT_SESSION").is_ok();
+
+    let seq = if osc9 {
+        format!("\x1B]9;{}\x07", body)
     } else {
-        None
+        format!("\x1B]777;notify;AeroShell;{}\x07", body)
+    };
+    print!("{}", seq);
+    let _ = std::io::stdout().flush();
+}
+
+fn print_help(config: &RootConfig) {
+    let header_c = theme::resolve(&config.theme.header, config).prefix();
+    let subheader_c = theme::resolve(&config.theme.subheader, config).prefix();
+    let body_c = theme::resolve(&config.theme.body, config).prefix();
+    let active_c = theme::resolve(&config.theme.active, config).prefix();
+    let reset = theme::reset();
+
+    println!("\n{}AeroShell Built-in Commands:{}", header_c, reset);
+    println!("{}", "=".repeat(30));
+
+    let commands = [
+        ("cd", "<dir>", "Change directory"),
+        ("ls", "[-l] [dir]", "List files (-a hidden, -h sizes, -t/-S sort, --tree, --icons, --json)"),
+        ("proc", "<sub>", "Process monitor (see below)"),
+        ("exit", "", "Exit shell"),
+        ("clear", "", "Clear screen"),
+        ("config", "", "Open configuration"),
+        ("aero", "<cmd>", "Manage AeroShell"),
+        ("help", "", "Show this help"),
+    ];
+
+    for (cmd, args, desc) in commands {
+        println!("  {}{:<10}{} {}{:<10}{} - {}{}{}",
+            active_c, cmd, reset,
+            subheader_c, args, reset,
+            body_c, desc, reset
+        );
     }
+    println!("\n{}Usage Tips:{}", header_c, reset);
+    println!("  - proc subcommands: top, mem, cpu, tree, gpu, disk, net, sys, temp, info, kill, signal.");
+    println!("  - Use 'proc mem' to check memory usage.");
+    println!("  - Use 'aero update <zip>' to update from source.");
+    println!();
 }
 
-// Helper to convert config color name to nu-ansi-term Style for Reedline
-fn get_style_from_config(color_name: &str, config: &RootConfig) -> nu_ansi_term::Style {
-    // 1. Check custom colors (Hex)
-    if let Some(hex) = config.colors.get(color_name) {
-        if let Some((r, g, b)) = hex_to_rgb(hex) {
-            return nu_ansi_term::Style::new().fg(nu_ansi_term::Color::Rgb(r, g, b));
-        }
+// Exposes the theme to child processes (see "Developing for AeroShell").
+// Each variable also gets a `_SGR` twin holding the ready-to-print escape
+// sequence, downgraded to what this terminal supports.
+fn export_theme_env(config: &RootConfig) {
+    // Drop colors a reloaded config no longer defines.
+    let stale: Vec<String> = env::vars().map(|(k, _)| k).filter(|k| k.starts_with("AERO_COLOR_")).collect();
+    for var in stale {
+        env::remove_var(var);
+    }
+    for (name, value) in &config.colors {
+        let var = format!("AERO_COLOR_{}", name.to_uppercase());
+        env::set_var(&var, value);
+        env::set_var(format!("{}_SGR", var), theme::resolve(name, config).prefix());
     }
+    let t = &config.theme;
+    let fields = [
+        ("AUTOCOMPLETE", &t.autocomplete),
+        ("TYPING", &t.typing),
+        ("TYPINGTEXT", &t.typingtext),
+        ("HEADER", &t.header),
+        ("SUBHEADER", &t.subheader),
+        ("BODY", &t.body),
+        ("ACTIVE", &t.active),
+        ("DISABLE", &t.disable),
+    ];
+    for (name, value) in fields {
+        env::set_var(format!("AERO_THEME_{}", name), value);
+        env::set_var(format!("AERO_THEME_{}_SGR", name), theme::resolve(value, config).prefix());
+    }
+    env::set_var("AERO_RESET_SGR", theme::reset());
 
-    // 2. Check built-ins
-    match color_name {
-        "black" => nu_ansi_term::Style::new().fg(nu_ansi_term::Color::Black),
-        "red" => nu_ansi_term::Style::new().fg(nu_ansi_term::Color::Red),
-        "green" => nu_ansi_term::Style::new().fg(nu_ansi_term::Color::Green),
-        "yellow" => nu_ansi_term::Style::new().fg(nu_ansi_term::Color::Yellow),
-        "blue" => nu_ansi_term::Style::new().fg(nu_ansi_term::Color::Blue),
-        "magenta" => nu_ansi_term::Style::new().fg(nu_ansi_term::Color::Magenta),
-        "cyan" => nu_ansi_term::Style::new().fg(nu_ansi_term::Color::Cyan),
-        "white" => nu_ansi_term::Style::new().fg(nu_ansi_term::Color::White),
-        "grey" | "gray" => nu_ansi_term::Style::new().fg(nu_ansi_term::Color::LightGray),
-        _ => nu_ansi_term::Style::default(),
+    if config.config.export_ls_colors {
+        env::set_var("LS_COLORS", theme_export::ls_colors(config, theme::color_support()));
     }
 }
 
-// Convert config color to simple ANSI string
-fn get_ansi_from_config(color_name: &str, config: &RootConfig) -> String {
-    if let Some(hex) = config.colors.get(color_name) {
-        if let Some((r, g, b)) = hex_to_rgb(hex) {
-            return format!("\x1B[38;2;{};{};{}m", r, g, b);
-        }
-    }
-    match color_name {
-        "black" => "\x1B[30m".to_string(),
-        "red" => "\x1B[31m".to_string(),
-        "green" => "\x1B[32m".to_string(),
-        "yellow" => "\x1B[33m".to_string(),
-        "blue" => "\x1B[34m".to_string(),
-        "magenta" => "\x1B[35m".to_string(),
-        "cyan" => "\x1B[36m".to_string(),
-        "white" => "\x1B[37m".to_string(),
-        "grey" | "gray" => "\x1B[90m".to_string(),
-        _ => "\x1B[0m".to_string(),
+// Sample `ls` output built from theme.files keys rather than a real directory.
+fn print_sample_ls(config: &RootConfig) {
+    let samples = [
+        ("src", "directory"),
+        ("build.sh", "executable"),
+        ("main.rs", "rust"),
+        ("app.py", "python"),
+        ("index.js", "javascript"),
+        ("Cargo.toml", "toml"),
+        ("release.zip", "file.zip"),
+        ("latest", "symlink"),
+        (".env", "hidden"),
+        ("notes.txt", "default"),
+    ];
+    for (name, key) in samples {
+        let color_name = config.theme.files.get(key)
+            .or_else(|| config.theme.files.get("default"))
+            .map(|s| s.as_str())
+            .unwrap_or("white");
+        print!("{}  ", theme::resolve(color_name, config).paint(name));
     }
+    println!();
 }
 
-fn cmd_ls(args: &[&str], config: &RootConfig) {
-    let target = if args.is_empty() { "." } else { args[0] };
-
-    // Read dir
-    match fs::read_dir(target) {
-        Ok(entries) => {
-            let mut entries: Vec<_> = entries.filter_map(|e| e.ok()).collect();
-            // Sort by name
-            entries.sort_by_key(|e| e.file_name());
-
-            for entry in entries {
-                let path = entry.path();
-                let name = entry.file_name().to_string_lossy().to_string();
-                let metadata = entry.metadata().ok();
-
-                let mut color_key = String::from("default");
-
-                if path.is_dir() {
-                    color_key = String::from("directory");
-                } else if let Some(m) = metadata {
-                    let mut is_exe = false;
-                    #[cfg(unix)]
-                    {
-                        if m.permissions().mode() & 0o111 != 0 {
-                            is_exe = true;
-                        }
-                    }
+// Re-reads config.toml and re-applies what was set up from the old one: the
+// theme environment child processes see and the hinter's color.
+fn reload_config(config: &mut RootConfig, line_editor: Reedline) -> Reedline {
+    *config = load_config();
+    export_theme_env(config);
+    let hint_style = theme::resolve(&config.theme.autocomplete, config).to_nu();
+    line_editor.with_hinter(Box::new(DefaultHinter::default().with_style(hint_style)))
+}
 
-                    if is_exe {
-                        color_key = String::from("executable");
+// `aero theme ...`. Returns true when the config on disk changed.
+fn cmd_theme(args: &[&str], config: &RootConfig) -> bool {
+    let header_c = theme::resolve(&config.theme.header, config).prefix();
+    let subheader_c = theme::resolve(&config.theme.subheader, config).prefix();
+    let body_c = theme::resolve(&config.theme.body, config).prefix();
+    let active_c = theme::resolve(&config.theme.active, config).prefix();
+    let err_c = theme::resolve(&config.theme.disable, config).prefix();
+    let reset = theme::reset();
+
+    match args.first().copied() {
+        Some("list") => {
+            println!("{}Themes:{}", header_c, reset);
+            let current = theme::selected_theme_name(config);
+            for (name, source) in theme::list_themes() {
+                let marker = if name == current { "*" } else { " " };
+                let origin = match source {
+                    theme::ThemeSource::Builtin => "built-in".to_string(),
+                    theme::ThemeSource::User(path) => path.display().to_string(),
+                };
+                println!("  {}{}{} {}{:<18}{} {}{}{}",
+                    active_c, marker, reset,
+                    subheader_c, name, reset,
+                    body_c, origin, reset);
+            }
+            if config.config.theme.is_empty() {
+                println!("\n{}Using the [theme] section of config.toml.{}", body_c, reset);
+            } else if config.config.theme == "auto" {
+                println!("\n{}auto: {} on light backgrounds, {} on dark ones.{}",
+                    body_c, config.config.theme_light, config.config.theme_dark, reset);
+            }
+            false
+        },
+        Some("preview") => {
+            let current = theme::selected_theme_name(config);
+            let name = args.get(1).copied().unwrap_or(current.as_str());
+            let mut preview = config.clone();
+            if !name.is_empty() {
+                match theme::load_theme(name) {
+                    Ok(t) => theme::apply_theme(&mut preview, t),
+                    Err(e) => {
+                        eprintln!("{}Theme error: {}{}", err_c, e, reset);
+                        return false;
                     }
+                }
+            }
 
-                    // Check extension dynamically against config
-                    if let Some(ext) = path.extension().and_then(|s| s.to_str()) {
-                        let file_key = format!("file.{}", ext);
-                        if config.theme.files.contains_key(&file_key) {
-                            color_key = file_key;
-                        } else if config.theme.files.contains_key(ext) {
-                            color_key = ext.to_string();
-                        } else {
-                            // Legacy/Helper mappings
-                            match ext {
-                                "py" => if config.theme.files.contains_key("python") { color_key = String::from("python"); },
-                                "sh" => if config.theme.files.contains_key("shellscript") { color_key = String::from("shellscript"); },
-                                "rs" => if config.theme.files.contains_key("rust") { color_key = String::from("rust"); },
-                                "js" => if config.theme.files.contains_key("javascript") { color_key = String::from("javascript"); },
-                                _ => {}
-                            }
-                        }
+            let state = PromptState {
+                last_duration: Some(Duration::from_millis(4200)),
+                segments: [("git_branch", "main*".to_string())].into_iter().collect(),
+            };
+            println!("{}Prompt:{}", header_c, reset);
+            println!("  {}ls -a{}", format_prompt(&preview.theme.prompt_template, &preview, &state), reset);
+            println!("\n{}ls:{}", header_c, reset);
+            print!("  ");
+            print_sample_ls(&preview);
+            print_help(&preview);
+            false
+        },
+        Some(cmd @ ("set" | "auto")) if cmd == "auto" || args.len() > 1 => {
+            let name = match cmd {
+                "auto" => "auto",
+                _ if args[1] == "none" => "",
+                _ => args[1],
+            };
+            if name == "auto" {
+                let (kind, chosen) = match terminal::refresh_background() {
+                    terminal::Background::Light => ("light", &config.config.theme_light),
+                    terminal::Background::Dark => ("dark", &config.config.theme_dark),
+                };
+                println!("{}Detected a {} background, using {}.{}", body_c, kind, chosen, reset);
+            } else if !name.is_empty() {
+                if let Err(e) = theme::load_theme(name) {
+                    eprintln!("{}Theme error: {}{}", err_c, e, reset);
+                    return false;
+                }
+            }
+            match set_config_value("theme", name) {
+                Ok(()) => {
+                    if name.is_empty() {
+                        println!("{}Using the [theme] section of config.toml again.{}", active_c, reset);
+                    } else {
+                        println!("{}Theme set to {}.{}", active_c, name, reset);
                     }
+                    true
+                },
+                Err(e) => {
+                    eprintln!("{}Failed to save config: {}{}", err_c, e, reset);
+                    false
                 }
-
-                // Lookup color name
-                let file_color_name = config.theme.files.get(&color_key)
-                    .or_else(|| config.theme.files.get("default"))
-                    .map(|s| s.as_str())
-                    .unwrap_or("white");
-
-                let color_ansi = get_ansi_from_config(file_color_name, config);
-                let reset = "\x1B[0m";
-
-                print!("{}{}{}  ", color_ansi, name, reset);
             }
-            println!();
         },
-        Err(e) => eprintln!("ls: {}", e),
-    }
-}
-
-fn cmd_proc(args: &[&str], config: &RootConfig) {
-    let mut sys = System::new_all();
-    sys.refresh_all();
-    // We only refresh processes specifically? new_all refreshes everything once.
-    // To be strictly up to date on subsequent calls we might need refresh, but this command runs once per invocation.
-
-    let header_c = get_ansi_from_config(&config.theme.header, config);
-    let subheader_c = get_ansi_from_config(&config.theme.subheader, config);
-    let body_c = get_ansi_from_config(&config.theme.body, config);
-    let active_c = get_ansi_from_config(&config.theme.active, config);
-    let reset = "\x1B[0m";
-
-    if args.is_empty() {
-        println!("\n{}Process Monitor (proc):{}", header_c, reset);
-        println!("{}", "=".repeat(30));
-
-        let commands = [
-            ("mem", "", "Show top memory consumers"),
-            ("cpu", "", "Show top CPU consumers"),
-            ("gpu", "", "Show GPU/System memory info"),
-            ("<name>", "", "Search processes by name"),
-        ];
-
-        for (cmd, args, desc) in commands {
-            println!("  {}{:<10}{} {}{:<10}{} - {}{}{}",
-                active_c, cmd, reset,
-                subheader_c, args, reset,
-                body_c, desc, reset
-            );
-        }
-        println!("\n{}Usage:{} proc [mem|cpu|gpu|<name>]", header_c, reset);
-        return;
-    }
-
-    // Collect processes into a simplified struct for sorting/printing
-    struct ProcInfo {
-        pid: sysinfo::Pid,
-        name: String,
-        memory: u64, // bytes
-        cpu: f32,    // usage %
-    }
+        Some("export") => {
+            let mut format = "toml";
+            let mut name = None;
+            let mut rest = args[1..].iter();
+            while let Some(arg) = rest.next() {
+                if let Some(f) = arg.strip_prefix("--format=") {
+                    format = f;
+                } else if *arg == "--format" || *arg == "-f" {
+                    format = rest.next().copied().unwrap_or("");
+                } else {
+                    name = Some(*arg);
+                }
+            }
 
-    let mut procs: Vec<ProcInfo> = sys.processes().iter().map(|(pid, p)| {
-        ProcInfo {
-            pid: *pid,
-            name: p.name().to_string_lossy().to_string(),
-            memory: p.memory(),
-            cpu: p.cpu_usage(),
-        }
-    }).collect();
-
-    match args[0] {
-        "mem" => {
-            println!("{}Top Memory Consumers:{}", header_c, reset);
-            println!("{:<8} {:<25} {:>15}", "PID", "Name", "Memory (MB)");
-            println!("{}", "=".repeat(50));
-
-            procs.sort_by(|a, b| b.memory.cmp(&a.memory));
-            for p in procs.iter().take(10) {
-                let mem_mb = p.memory as f32 / 1024.0 / 1024.0;
-                println!("{:<8} {}{:<25}{} {:>15.2}",
-                    p.pid, subheader_c, p.name, reset, mem_mb);
+            // A named theme is exported on its own, without the user's colors.
+            let mut source = config.clone();
+            if let Some(name) = name {
+                match theme::load_theme(name) {
+                    Ok(t) => {
+                        source.theme = t.theme;
+                        source.colors = t.colors;
+                    },
+                    Err(e) => {
+                        eprintln!("{}Theme error: {}{}", err_c, e, reset);
+                        return false;
+                    }
+                }
             }
-        },
-        "cpu" => {
-            println!("{}Top CPU Consumers:{}", header_c, reset);
-            println!("{:<8} {:<25} {:>10}", "PID", "Name", "CPU %");
-            println!("{}", "=".repeat(45));
-
-            procs.sort_by(|a, b| b.cpu.partial_cmp(&a.cpu).unwrap_or(std::cmp::Ordering::Equal));
-            for p in procs.iter().take(10) {
-                println!("{:<8} {}{:<25}{} {:>10.2}",
-                    p.pid, subheader_c, p.name, reset, p.cpu);
+            let current = theme::selected_theme_name(config);
+            let label = name.unwrap_or(if current.is_empty() { "custom" } else { &current });
+            match theme_export::export(&source, label, format) {
+                Ok(content) => print!("{}", content),
+                Err(e) => eprintln!("{}Export failed: {}{}", err_c, e, reset),
             }
-        },
-        "gpu" => {
-            // sysinfo doesn't support GPU usage directly.
-            // On Apple Silicon (Asahi), GPU memory is unified.
-            // We can perhaps show total system memory usage as a proxy or just list standard processes
-            // emphasizing it's shared memory.
-            println!("{}GPU/Unified Memory Info:{}", header_c, reset);
-            println!("{}Note: Granular GPU process usage is not standardly available via sysinfo.{}", body_c, reset);
-            println!("Showing total system memory usage (Shared):");
-
-            let total_mem = sys.total_memory() as f32 / 1024.0 / 1024.0 / 1024.0; // GB
-            let used_mem = sys.used_memory() as f32 / 1024.0 / 1024.0 / 1024.0;
-
-            println!("  Total: {:.2} GB", total_mem);
-            println!("  Used:  {:.2} GB", used_mem);
+            false
         },
         _ => {
-            // Filter by name (comma separated)
-            // Join all args to handle spaces if split by shell (though we use comma logic per request)
-            // If user types "proc firefox, discord", args might be ["firefox,", "discord"] depending on shlex.
-            // Let's rejoin and split by comma.
-            let query = args.join(" ");
-            let targets: Vec<&str> = query.split(',').map(|s| s.trim()).collect();
-
-            println!("{}Searching processes for: {:?}{}", header_c, targets, reset);
-            println!("{:<8} {:<25} {:>10} {:>15}", "PID", "Name", "CPU %", "Memory (MB)");
-            println!("{}", "=".repeat(65));
-
-            let matches: Vec<_> = procs.iter().filter(|p| {
-                let name_lower = p.name.to_lowercase();
-                targets.iter().any(|t| name_lower.contains(&t.to_lowercase()))
-            }).collect();
-
-            if matches.is_empty() {
-                println!("{}No matching processes found.{}", body_c, reset);
-            } else {
-                for p in matches {
-                    let mem_mb = p.memory as f32 / 1024.0 / 1024.0;
-                    println!("{:<8} {}{:<25}{} {:>10.2} {:>15.2}",
-                        p.pid, subheader_c, p.name, reset, p.cpu, mem_mb);
-                }
+            println!("\n{}Theme Manager (aero theme):{}", header_c, reset);
+            println!("{}", "=".repeat(30));
+
+            let commands = [
+                ("list", "", "List built-in and user themes"),
+                ("preview", "[name]", "Show a sample prompt, ls and help"),
+                ("set", "<name|none|auto>", "Switch to a theme"),
+                ("auto", "", "Follow the terminal's light/dark background"),
+                ("export", "[name] [--format=F]", "Print a theme as toml, alacritty, kitty, wezterm or ls_colors"),
+            ];
+
+            for (cmd, args, desc) in commands {
+                println!("  {}{:<10}{} {}{:<20}{} - {}{}{}",
+                    active_c, cmd, reset,
+                    subheader_c, args, reset,
+                    body_c, desc, reset
+                );
             }
+            println!("\n{}Themes folder:{} {}", header_c, reset, get_themes_dir().display());
+            false
         }
     }
 }
@@ -271,21 +318,14 @@ fn cmd_proc(args: &[&str], config: &RootConfig) {
 fn main() {
     ctrlc::set_handler(move || {}).expect("Error setting Ctrl-C handler");
 
+    let shlvl = env::var("SHLVL").ok().and_then(|v| v.parse::<u32>().ok()).unwrap_or(0);
+    env::set_var("SHLVL", (shlvl + 1).to_string());
+
+    theme::set_color_support(terminal::detect_color_support());
+
     let mut config = load_config();
 
-    // Export Env Vars
-    for (name, hex) in &config.colors {
-        env::set_var(format!("AERO_COLOR_{}", name.to_uppercase()), hex);
-    }
-    let t = &config.theme;
-    env::set_var("AERO_THEME_AUTOCOMPLETE", &t.autocomplete);
-    env::set_var("AERO_THEME_TYPING", &t.typing);
-    env::set_var("AERO_THEME_TYPINGTEXT", &t.typingtext);
-    env::set_var("AERO_THEME_HEADER", &t.header);
-    env::set_var("AERO_THEME_SUBHEADER", &t.subheader);
-    env::set_var("AERO_THEME_BODY", &t.body);
-    env::set_var("AERO_THEME_ACTIVE", &t.active);
-    env::set_var("AERO_THEME_DISABLE", &t.disable);
+    export_theme_env(&config);
 
     let history_path = env::var("HOME")
         .map(|h| format!("{}/.aeroshell_history", h))
@@ -296,36 +336,30 @@ fn main() {
             .expect("Error configuring history with file"),
     );
 
-    let hint_style = get_style_from_config(&config.theme.autocomplete, &config);
+    let hint_style = theme::resolve(&config.theme.autocomplete, &config).to_nu();
 
     let mut line_editor = Reedline::create()
+        .with_ansi_colors(theme::color_support() != terminal::ColorSupport::None)
         .with_history(history)
         .with_hinter(Box::new(DefaultHinter::default().with_style(hint_style)))
         .with_completer(Box::new(AeroCompleter));
 
-    loop {
-        let prompt_str = format_prompt(&config.theme.prompt_template, &config);
-
-        struct AeroPrompt(String);
-        impl reedline::Prompt for AeroPrompt {
-            fn render_prompt_left(&self) -> std::borrow::Cow<'_, str> {
-                std::borrow::Cow::Borrowed(&self.0)
-            }
-            fn render_prompt_right(&self) -> std::borrow::Cow<'_, str> {
-                std::borrow::Cow::Borrowed("")
-            }
-            fn render_prompt_indicator(&self, _prompt_mode: reedline::PromptEditMode) -> std::borrow::Cow<'_, str> {
-                std::borrow::Cow::Borrowed("")
-            }
-            fn render_prompt_multiline_indicator(&self) -> std::borrow::Cow<'_, str> {
-                 std::borrow::Cow::Borrowed(".. ")
-            }
-            fn render_prompt_history_search_indicator(&self, _history_search: reedline::PromptHistorySearch) -> std::borrow::Cow<'_, str> {
-                std::borrow::Cow::Borrowed("(search) ")
-            }
-        }
+    let mut prompt_state = PromptState::default();
+    let segment_cache = SegmentCache::new();
 
-        let prompt = AeroPrompt(prompt_str);
+    loop {
+        let cwd = env::current_dir().unwrap_or_default();
+        segment_cache.refresh(
+            &config.theme.prompt_template,
+            &cwd,
+            Duration::from_millis(config.config.segment_timeout_ms),
+        );
+
+        let prompt = AeroPrompt {
+            config: &config,
+            state: &prompt_state,
+            segments: &segment_cache,
+        };
 
         match line_editor.read_line(&prompt) {
             Ok(Signal::Success(buffer)) => {
@@ -349,6 +383,10 @@ fn main() {
                 let command = &parts[0];
                 let args: Vec<&str> = parts[1..].iter().map(|s| s.as_str()).collect();
 
+                // Builtins that bail out early with `continue` leave this unset.
+                prompt_state.last_duration = None;
+                let started = Instant::now();
+
                 match command.as_str() {
                     "cd" => {
                         let new_dir = if args.is_empty() {
@@ -371,10 +409,10 @@ fn main() {
                         }
                     },
                     "ls" => {
-                        cmd_ls(&args, &config);
+                        ls::cmd_ls(&args, &config);
                     },
                     "proc" => {
-                        cmd_proc(&args, &config);
+                        proc::cmd_proc(&args, &config);
                     },
                     "exit" => break,
                     "clear" => {
@@ -382,24 +420,25 @@ fn main() {
                     },
                     "config" => {
                         open_config(&config);
-                        config = load_config();
+                        line_editor = reload_config(&mut config, line_editor);
                     },
                     "aero" => {
-                         let header_c = get_ansi_from_config(&config.theme.header, &config);
-                         let subheader_c = get_ansi_from_config(&config.theme.subheader, &config);
-                         let body_c = get_ansi_from_config(&config.theme.body, &config);
-                         let active_c = get_ansi_from_config(&config.theme.active, &config);
-                         let err_c = get_ansi_from_config(&config.theme.disable, &config);
-                         let reset = "\x1B[0m";
+                         let header_c = theme::resolve(&config.theme.header, &config).prefix();
+                         let subheader_c = theme::resolve(&config.theme.subheader, &config).prefix();
+                         let body_c = theme::resolve(&config.theme.body, &config).prefix();
+                         let active_c = theme::resolve(&config.theme.active, &config).prefix();
+                         let err_c = theme::resolve(&config.theme.disable, &config).prefix();
+                         let reset = theme::reset();
 
                          if args.is_empty() {
                             println!("\n{}AeroShell Manager (aero):{}", header_c, reset);
                             println!("{}", "=".repeat(30));
 
                             let commands = [
-                                ("about", "", "Show version and build info"),
-                                ("config", "", "Open configuration in editor"),
+                                ("about", "[--json]", "Show version and build info"),
+                                ("config", "[check]", "Open configuration in editor, or check it"),
                                 ("setdefault", "", "Set AeroShell as default shell"),
+                                ("theme", "<cmd>", "List, preview and switch themes"),
                                 ("update", "[-d] <zip>", "Update AeroShell from a source zip"),
                             ];
 
@@ -414,14 +453,49 @@ fn main() {
                         } else {
                             match args[0] {
                                 "about" => {
-                                    println!("\n{}AeroShell{}", header_c, reset);
-                                    println!("{}", "=".repeat(20));
-                                    println!("{}Version:{} {}", subheader_c, reset, crate::version::get_version_description());
-                                    println!();
+                                    let info = crate::version::build_info();
+                                    let config_path = get_config_path().to_string_lossy().to_string();
+                                    match args.get(1).and_then(|a| output::Format::from_flag(a)) {
+                                        Some(output::Format::Json) => {
+                                            output::print_json(&serde_json::json!({
+                                                "version": info.version,
+                                                "build_type": info.build_type,
+                                                "git_hash": info.git_hash,
+                                                "config_path": config_path,
+                                            }));
+                                        },
+                                        Some(_) => output::print_tsv(
+                                            &["version", "build_type", "git_hash", "config_path"],
+                                            [vec![
+                                                info.version,
+                                                info.build_type.to_string(),
+                                                info.git_hash.unwrap_or_default().to_string(),
+                                                config_path,
+                                            ]],
+                                        ),
+                                        None => {
+                                            println!("\n{}AeroShell{}", header_c, reset);
+                                            println!("{}", "=".repeat(20));
+                                            println!("{}Version:{} {}", subheader_c, reset, crate::version::get_version_description());
+                                            if let Some(hash) = info.git_hash {
+                                                println!("{}Commit:{}  {}", subheader_c, reset, hash);
+                                            }
+                                            println!("{}Config:{}  {}", subheader_c, reset, config_path);
+                                            println!();
+                                        },
+                                    }
+                                },
+                                "config" if args.get(1) == Some(&"check") => {
+                                    config_check::cmd_check(&config);
                                 },
                                 "config" => {
                                     open_config(&config);
-                                    config = load_config();
+                                    line_editor = reload_config(&mut config, line_editor);
+                                },
+                                "theme" => {
+                                    if cmd_theme(&args[1..], &config) {
+                                        line_editor = reload_config(&mut config, line_editor);
+                                    }
                                 },
                                 "setdefault" => {
                                     println!("{}Setting AeroShell as default shell...{}", header_c, reset);
@@ -431,10 +505,10 @@ fn main() {
                                         let mut needs_add = true;
                                         if let Ok(mut file) = fs::File::open("/etc/shells") {
                                             let mut contents = String::new();
-                                            if file.read_to_string(&mut contents).is_ok() {
-                                                if contents.lines().any(|line| line.trim() == path_str) {
-                                                    needs_add = false;
-                                                }
+                                            if file.read_to_string(&mut contents).is_ok()
+                                                && contents.lines().any(|line| line.trim() == path_str)
+                                            {
+                                                needs_add = false;
                                             }
                                         }
 
@@ -495,39 +569,7 @@ fn main() {
                             }
                         }
                     },
-                    "help" => {
-                        let header_c = get_ansi_from_config(&config.theme.header, &config);
-                        let subheader_c = get_ansi_from_config(&config.theme.subheader, &config);
-                        let body_c = get_ansi_from_config(&config.theme.body, &config);
-                        let active_c = get_ansi_from_config(&config.theme.active, &config);
-                        let reset = "\x1B[0m";
-
-                        println!("\n{}AeroShell Built-in Commands:{}", header_c, reset);
-                        println!("{}", "=".repeat(30));
-
-                        let commands = [
-                            ("cd", "<dir>", "Change directory"),
-                            ("ls", "[dir]", "List files (colored)"),
-                            ("proc", "[mem|cpu|name]", "Process monitor"), // Added description
-                            ("exit", "", "Exit shell"),
-                            ("clear", "", "Clear screen"),
-                            ("config", "", "Open configuration"),
-                            ("aero", "<cmd>", "Manage AeroShell"),
-                            ("help", "", "Show this help"),
-                        ];
-
-                        for (cmd, args, desc) in commands {
-                            println!("  {}{:<10}{} {}{:<10}{} - {}{}{}",
-                                active_c, cmd, reset,
-                                subheader_c, args, reset,
-                                body_c, desc, reset
-                            );
-                        }
-                        println!("\n{}Usage Tips:{}", header_c, reset);
-                        println!("  - Use 'proc mem' to check memory usage.");
-                        println!("  - Use 'aero update <zip>' to update from source.");
-                        println!();
-                    },
+                    "help" => print_help(&config),
                     cmd => {
                         let child_result = Command::new(cmd)
                             .args(args)
@@ -548,6 +590,14 @@ fn main() {
                         }
                     }
                 }
+
+                let elapsed = started.elapsed();
+                prompt_state.last_duration = Some(elapsed);
+                if config.config.notify_long_commands
+                    && elapsed >= Duration::from_millis(config.config.duration_threshold_ms)
+                {
+                    notify_command_finished(command, elapsed);
+                }
             }
             Ok(Signal::CtrlD) => {
                 println!("Use 'exit' to quit.");
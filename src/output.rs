@@ -0,0 +1,70 @@
+use serde::Serialize;
+
+/// How a built-in prints its results: the colored table, or something
+/// scripts can parse.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Format {
+    #[default]
+    Table,
+    Json,
+    Tsv,
+}
+
+impl Format {
+    /// `--json` or `--tsv`; `None` for any other argument.
+    pub fn from_flag(arg: &str) -> Option<Format> {
+        match arg {
+            "--json" => Some(Format::Json),
+            "--tsv" => Some(Format::Tsv),
+            _ => None,
+        }
+    }
+}
+
+pub fn print_json<T: Serialize + ?Sized>(value: &T) {
+    match serde_json::to_string_pretty(value) {
+        Ok(json) => println!("{}", json),
+        Err(e) => eprintln!("json: {}", e),
+    }
+}
+
+/// A header line, then one tab-separated line per row.
+pub fn print_tsv<I>(header: &[&str], rows: I)
+where
+    I: IntoIterator<Item = Vec<String>>,
+{
+    println!("{}", header.join("\t"));
+    for row in rows {
+        let fields: Vec<String> = row.iter().map(|f| escape_tsv(f)).collect();
+        println!("{}", fields.join("\t"));
+    }
+}
+
+/// Backslash-escapes what would break a TSV line.
+fn escape_tsv(field: &str) -> String {
+    let mut escaped = String::with_capacity(field.len());
+    for c in field.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_flags_and_escaping() {
+        assert_eq!(Format::from_flag("--json"), Some(Format::Json));
+        assert_eq!(Format::from_flag("--tsv"), Some(Format::Tsv));
+        assert_eq!(Format::from_flag("-l"), None);
+        assert_eq!(escape_tsv("a\tb\nc\\d"), "a\\tb\\nc\\\\d");
+        assert_eq!(escape_tsv("plain name"), "plain name");
+    }
+}
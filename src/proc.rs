@@ -0,0 +1,682 @@
+use std::io::Write;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use sysinfo::{Process, ProcessRefreshKind, ProcessesToUpdate, System, ThreadKind, UpdateKind, Users, MINIMUM_CPU_UPDATE_INTERVAL};
+
+use crate::config::RootConfig;
+use crate::ls::human_size;
+use crate::gpu;
+use crate::output::{self, Format};
+use crate::proc_info;
+use crate::proc_system;
+use crate::proc_top;
+use crate::proc_tree;
+use crate::theme;
+
+pub fn cmd_proc(args: &[&str], config: &RootConfig) {
+    if args.first() == Some(&"top") {
+        if let Err(e) = proc_top::run(config) {
+            eprintln!("proc top: {}", e);
+        }
+        return;
+    }
+
+    let header_c = theme::resolve(&config.theme.header, config).prefix();
+    let subheader_c = theme::resolve(&config.theme.subheader, config).prefix();
+    let body_c = theme::resolve(&config.theme.body, config).prefix();
+    let active_c = theme::resolve(&config.theme.active, config).prefix();
+    let reset = theme::reset();
+
+    if args.is_empty() {
+        println!("\n{}Process Monitor (proc):{}", header_c, reset);
+        println!("{}", "=".repeat(30));
+
+        let commands = [
+            ("top", "", "Live view: sort, filter and kill"),
+            ("mem", "[options]", "Show top memory consumers"),
+            ("cpu", "[options]", "Show top CPU consumers"),
+            ("tree", "[pid|name]", "Process tree with subtree totals"),
+            ("gpu", "", "GPU utilization and per-process GPU memory"),
+            ("disk", "", "Mounted filesystems and usage"),
+            ("net", "", "Network interface rates"),
+            ("sys", "", "Uptime, load, kernel, CPU model and swap"),
+            ("temp", "", "Temperature sensors"),
+            ("info", "<pid>", "Details of one process (--env: environment)"),
+            ("kill", "<name|pid>", "Signal processes (-s SIGNAL, -y: no prompt)"),
+            ("signal", "<SIG> <name|pid>", "Send SIG to matching processes"),
+            ("<name>", "", "Search processes by name"),
+        ];
+
+        for (cmd, args, desc) in commands {
+            println!("  {}{:<10}{} {}{:<10}{} - {}{}{}",
+                active_c, cmd, reset,
+                subheader_c, args, reset,
+                body_c, desc, reset
+            );
+        }
+        println!("\n{}Usage:{} proc [top|mem|cpu|tree|gpu|disk|net|sys|temp|info|kill|signal|<name>]", header_c, reset);
+        println!("{}Options:{} -n N (rows, 0 = all), --user [NAME], --sort cpu|mem|pid|name, --json, --tsv (mem, cpu, tree, info, <name>)", header_c, reset);
+        return;
+    }
+
+    // Only the process lists and `info` have structured output.
+    if matches!(args[0], "gpu" | "disk" | "net" | "sys" | "temp") {
+        if let Some(flag) = args[1..].iter().find(|a| Format::from_flag(a).is_some()) {
+            eprintln!("proc {}: {} is not supported", args[0], flag);
+            return;
+        }
+    }
+
+    match args[0] {
+        "kill" => return cmd_kill(&args[1..], config),
+        "disk" => return proc_system::disk(config),
+        "net" => return proc_system::net(config),
+        "sys" => return proc_system::sys(config),
+        "temp" => return proc_system::temp(config),
+        "info" => return proc_info::print(&args[1..], config),
+        // `proc signal HUP nginx` is `proc kill nginx -s HUP`
+        "signal" => match args.get(1) {
+            Some(signal) => {
+                let mut rest = args[2..].to_vec();
+                rest.extend(["-s", signal]);
+                return cmd_kill(&rest, config);
+            }
+            None => {
+                eprintln!("Usage: proc signal <SIGNAL> <name|pid>[, ...]");
+                return;
+            }
+        },
+        _ => {}
+    }
+
+    let sys = sample_system();
+    let users = Users::new_with_refreshed_list();
+    let mut procs = collect_rows(&sys, &users);
+
+    match args[0] {
+        "mem" | "cpu" => {
+            let default_sort = if args[0] == "mem" { SortKey::Mem } else { SortKey::Cpu };
+            let opts = match ListOptions::parse(&args[1..], default_sort) {
+                Ok(opts) => opts,
+                Err(e) => {
+                    eprintln!("proc {}: {}", args[0], e);
+                    return;
+                }
+            };
+
+            if let Some(user) = &opts.user {
+                procs.retain(|p| &p.user == user);
+            }
+            sort_rows(&mut procs, opts.sort);
+            if opts.limit > 0 {
+                procs.truncate(opts.limit);
+            }
+
+            match opts.format {
+                Format::Json => {
+                    let summary = Summary::new(&sys);
+                    output::print_json(&serde_json::json!({ "summary": summary, "processes": procs }));
+                    return;
+                }
+                Format::Tsv => return print_tsv(&procs),
+                Format::Table => {}
+            }
+
+            let title = if args[0] == "mem" { "Top Memory Consumers:" } else { "Top CPU Consumers:" };
+            println!("{}{}{}", header_c, title, reset);
+            Summary::new(&sys).print(config);
+            println!();
+            println!("{:<8} {:<25} {:<12} {:>10} {:>15}", "PID", "Name", "User", "CPU %", "Memory (MB)");
+            println!("{}", "=".repeat(74));
+
+            for p in &procs {
+                let mem_mb = p.memory as f32 / 1024.0 / 1024.0;
+                println!("{:<8} {}{:<25}{} {:<12} {:>10.2} {:>15.2}",
+                    p.pid, subheader_c, p.name, reset, p.user, p.cpu, mem_mb);
+            }
+        },
+        "tree" => proc_tree::print(&procs, &args[1..], config),
+        "gpu" => {
+            let drm = Path::new("/sys/class/drm");
+            let devices = gpu::devices(drm);
+            let usage = if devices.is_empty() {
+                Vec::new()
+            } else {
+                let before = gpu::clients(Path::new("/proc"));
+                let started = Instant::now();
+                std::thread::sleep(GPU_SAMPLE_INTERVAL);
+                let after = gpu::clients(Path::new("/proc"));
+                gpu::process_usage(&devices, &before, &after, started.elapsed())
+            };
+
+            println!("{}GPU Devices:{}", header_c, reset);
+            if devices.is_empty() {
+                println!("{}No GPU found under {}.{}", body_c, drm.display(), reset);
+            }
+            for device in &devices {
+                let busy = gpu::device_busy(device, &usage)
+                    .map(|b| format!("{:.0}% busy", b))
+                    .unwrap_or_else(|| "utilization unavailable".to_string());
+                let vram = match (device.vram_used, device.vram_total) {
+                    (Some(used), Some(total)) => format!(", VRAM {} / {}", human_size(used), human_size(total)),
+                    _ => String::new(),
+                };
+                println!("  {}{:<6}{} {} ({}, {}) {}{}{}{}",
+                    active_c, device.card, reset,
+                    device.vendor, device.driver, device.slot,
+                    subheader_c, busy, vram, reset);
+            }
+
+            // Integrated GPUs (and Apple Silicon's unified memory) share system RAM.
+            println!("\n{}System Memory (shared with integrated GPUs):{}", header_c, reset);
+            println!("  Used {} of {}", human_size(sys.used_memory()), human_size(sys.total_memory()));
+
+            if !usage.is_empty() {
+                println!("\n{}GPU Processes:{}", header_c, reset);
+                println!("{:<8} {:<25} {:<8} {:>8} {:>12}", "PID", "Name", "Device", "GPU %", "GPU Memory");
+                println!("{}", "=".repeat(65));
+                for p in &usage {
+                    println!("{:<8} {}{:<25}{} {:<8} {:>8.1} {:>12}",
+                        p.pid, subheader_c, p.name, reset, p.card, p.busy, human_size(p.memory));
+                }
+            } else if !devices.is_empty() {
+                println!("\n{}No per-process GPU data (the driver may not expose it, or the processes belong to other users).{}", body_c, reset);
+            }
+        },
+        _ => {
+            // Filter by name (comma separated)
+            // Join all args to handle spaces if split by shell (though we use comma logic per request)
+            // If user types "proc firefox, discord", args might be ["firefox,", "discord"] depending on shlex.
+            // Let's rejoin and split by comma.
+            let format = args.iter().find_map(|a| Format::from_flag(a)).unwrap_or_default();
+            let words: Vec<&str> = args.iter().copied().filter(|a| Format::from_flag(a).is_none()).collect();
+            let query = words.join(" ");
+            let targets = split_targets(&query);
+
+            if format != Format::Table {
+                sort_rows(&mut procs, SortKey::Pid);
+                procs.retain(|p| name_matches(&p.name, &targets));
+                match format {
+                    Format::Json => output::print_json(&serde_json::json!({ "query": targets, "processes": procs })),
+                    _ => print_tsv(&procs),
+                }
+                return;
+            }
+
+            println!("{}Searching processes for: {:?}{}", header_c, targets, reset);
+            println!("{:<8} {:<25} {:>10} {:>15}", "PID", "Name", "CPU %", "Memory (MB)");
+            println!("{}", "=".repeat(65));
+
+            sort_rows(&mut procs, SortKey::Pid);
+            let matches: Vec<_> = procs.iter().filter(|p| name_matches(&p.name, &targets)).collect();
+
+            if matches.is_empty() {
+                println!("{}No matching processes found.{}", body_c, reset);
+            } else {
+                for p in matches {
+                    let mem_mb = p.memory as f32 / 1024.0 / 1024.0;
+                    println!("{:<8} {}{:<25}{} {:>10.2} {:>15.2}",
+                        p.pid, subheader_c, p.name, reset, p.cpu, mem_mb);
+                }
+            }
+        }
+    }
+}
+
+/// Options shared by `proc cpu` and `proc mem`.
+#[derive(Debug, PartialEq)]
+struct ListOptions {
+    /// Rows to show; 0 means all
+    limit: usize,
+    user: Option<String>,
+    sort: SortKey,
+    format: Format,
+}
+
+impl ListOptions {
+    fn parse(args: &[&str], default_sort: SortKey) -> Result<ListOptions, String> {
+        let mut opts = ListOptions { limit: 10, user: None, sort: default_sort, format: Format::Table };
+        let mut args = args.iter().copied().peekable();
+        while let Some(arg) = args.next() {
+            let (flag, inline) = match arg.split_once('=') {
+                Some((flag, value)) => (flag, Some(value)),
+                None => (arg, None),
+            };
+            // Without a name, --user means the user running the shell
+            let takes_value = match flag {
+                "-n" | "--sort" => true,
+                "--user" | "-u" => inline.is_some() || args.peek().is_some_and(|a| !a.starts_with('-')),
+                _ => false,
+            };
+            // The flag's value, inline (`--sort=mem`) or as the next argument
+            let value = if takes_value { inline.or_else(|| args.next()) } else { None };
+            let value = || value.ok_or_else(|| format!("{} needs a value", flag));
+
+            match flag {
+                "-n" => {
+                    let v = value()?;
+                    opts.limit = v.parse().map_err(|_| format!("invalid row count '{}'", v))?;
+                }
+                "--sort" => {
+                    let v = value()?;
+                    opts.sort = SortKey::parse(v).ok_or_else(|| format!("unknown sort '{}' (cpu, mem, pid, name)", v))?;
+                }
+                "--user" | "-u" => {
+                    opts.user = Some(value().map(|v| v.to_string()).unwrap_or_else(|_| crate::context::login_user()));
+                }
+                "--json" | "--tsv" => opts.format = Format::from_flag(flag).unwrap_or_default(),
+                other => return Err(format!("unknown option '{}'", other)),
+            }
+        }
+        Ok(opts)
+    }
+}
+
+/// How long `proc gpu` watches fdinfo engine counters to get utilization.
+const GPU_SAMPLE_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Two process samples `MINIMUM_CPU_UPDATE_INTERVAL` apart, so per-process
+/// and total CPU usage are real numbers rather than zeros.
+pub fn sample_system() -> System {
+    let mut sys = System::new();
+    refresh_processes(&mut sys);
+    sys.refresh_cpu_usage();
+    std::thread::sleep(MINIMUM_CPU_UPDATE_INTERVAL);
+    refresh_processes(&mut sys);
+    sys.refresh_cpu_usage();
+    sys.refresh_memory();
+    sys
+}
+
+/// Machine-wide totals shown above the process lists.
+#[derive(Serialize)]
+pub struct Summary {
+    pub cpu_usage: f32,
+    pub cores: usize,
+    pub load_average: [f64; 3],
+    pub memory_used: u64,
+    pub memory_total: u64,
+    pub swap_used: u64,
+    pub swap_total: u64,
+}
+
+impl Summary {
+    pub fn new(sys: &System) -> Summary {
+        let load = System::load_average();
+        Summary {
+            cpu_usage: sys.global_cpu_usage(),
+            cores: sys.cpus().len(),
+            load_average: [load.one, load.five, load.fifteen],
+            memory_used: sys.used_memory(),
+            memory_total: sys.total_memory(),
+            swap_used: sys.used_swap(),
+            swap_total: sys.total_swap(),
+        }
+    }
+
+    pub fn print(&self, config: &RootConfig) {
+        let label = theme::resolve(&config.theme.subheader, config);
+        let percent = |used: u64, total: u64| if total == 0 { 0.0 } else { used as f64 * 100.0 / total as f64 };
+        println!(
+            "{} {:.1}% of {} {}, load {:.2} {:.2} {:.2}",
+            label.paint("CPU:   "),
+            self.cpu_usage,
+            self.cores,
+            if self.cores == 1 { "core" } else { "cores" },
+            self.load_average[0],
+            self.load_average[1],
+            self.load_average[2]
+        );
+        println!(
+            "{} {} / {} ({:.0}%), swap {} / {}",
+            label.paint("Memory:"),
+            human_size(self.memory_used),
+            human_size(self.memory_total),
+            percent(self.memory_used, self.memory_total),
+            human_size(self.swap_used),
+            human_size(self.swap_total)
+        );
+    }
+}
+
+/// Signals `proc kill` understands by name, besides plain numbers.
+#[cfg(unix)]
+const SIGNALS: &[(&str, i32)] = &[
+    ("HUP", libc::SIGHUP),
+    ("INT", libc::SIGINT),
+    ("QUIT", libc::SIGQUIT),
+    ("KILL", libc::SIGKILL),
+    ("USR1", libc::SIGUSR1),
+    ("USR2", libc::SIGUSR2),
+    ("TERM", libc::SIGTERM),
+    ("STOP", libc::SIGSTOP),
+    ("CONT", libc::SIGCONT),
+];
+#[cfg(not(unix))]
+const SIGNALS: &[(&str, i32)] = &[("KILL", 9), ("TERM", 15)];
+
+/// Parses `TERM`, `SIGTERM`, `term` or `15` into a signal number and name.
+fn parse_signal(spec: &str) -> Result<(i32, String), String> {
+    if let Ok(number) = spec.parse::<i32>() {
+        let name = SIGNALS.iter().find(|(_, n)| *n == number).map(|(name, _)| format!("SIG{}", name));
+        return Ok((number, name.unwrap_or_else(|| format!("signal {}", number))));
+    }
+    let upper = spec.to_uppercase();
+    let bare = upper.strip_prefix("SIG").unwrap_or(&upper);
+    SIGNALS
+        .iter()
+        .find(|(name, _)| *name == bare)
+        .map(|(name, number)| (*number, format!("SIG{}", name)))
+        .ok_or_else(|| format!("unknown signal '{}'", spec))
+}
+
+#[derive(Debug, PartialEq)]
+struct KillArgs {
+    signal: i32,
+    signal_name: String,
+    pids: Vec<u32>,
+    names: Vec<String>,
+    /// -y: don't ask even when several processes match
+    yes: bool,
+}
+
+fn parse_kill_args(args: &[&str]) -> Result<KillArgs, String> {
+    let (signal, signal_name) = parse_signal("TERM")?;
+    let mut parsed = KillArgs { signal, signal_name, pids: Vec::new(), names: Vec::new(), yes: false };
+    let mut words = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match *arg {
+            "-s" | "--signal" => {
+                let spec = args.next().ok_or("-s needs a signal")?;
+                (parsed.signal, parsed.signal_name) = parse_signal(spec)?;
+            }
+            "-y" | "--yes" => parsed.yes = true,
+            // kill-style `-9` / `-KILL`
+            flag if flag.starts_with('-') && flag.len() > 1 => {
+                (parsed.signal, parsed.signal_name) = parse_signal(&flag[1..])?;
+            }
+            word => words.push(word),
+        }
+    }
+
+    let query = words.join(" ");
+    for target in split_targets(&query) {
+        match target.parse::<u32>() {
+            Ok(pid) => parsed.pids.push(pid),
+            Err(_) => parsed.names.push(target.to_string()),
+        }
+    }
+    if parsed.pids.is_empty() && parsed.names.is_empty() {
+        return Err("no process given".to_string());
+    }
+    Ok(parsed)
+}
+
+// `proc kill <name|pid>[, ...] [-s SIGNAL] [-y]`
+fn cmd_kill(args: &[&str], config: &RootConfig) {
+    let header_c = theme::resolve(&config.theme.header, config).prefix();
+    let subheader_c = theme::resolve(&config.theme.subheader, config).prefix();
+    let body_c = theme::resolve(&config.theme.body, config).prefix();
+    let active_c = theme::resolve(&config.theme.active, config).prefix();
+    let disable_c = theme::resolve(&config.theme.disable, config).prefix();
+    let reset = theme::reset();
+
+    let parsed = match parse_kill_args(args) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            eprintln!("proc kill: {}", e);
+            eprintln!("Usage: proc kill <name|pid>[, ...] [-s SIGNAL] [-y]");
+            return;
+        }
+    };
+
+    let mut sys = System::new();
+    refresh_processes(&mut sys);
+    let users = Users::new_with_refreshed_list();
+    let own_pid = std::process::id();
+    let names: Vec<&str> = parsed.names.iter().map(|n| n.as_str()).collect();
+
+    let mut matches: Vec<ProcRow> = collect_rows(&sys, &users)
+        .into_iter()
+        .filter(|p| parsed.pids.contains(&p.pid) || (p.pid != own_pid && name_matches(&p.name, &names)))
+        .collect();
+    sort_rows(&mut matches, SortKey::Pid);
+
+    for pid in &parsed.pids {
+        if !matches.iter().any(|p| p.pid == *pid) {
+            println!("{}No process with PID {}.{}", body_c, pid, reset);
+        }
+    }
+    if matches.is_empty() {
+        if !names.is_empty() {
+            println!("{}No matching processes found.{}", body_c, reset);
+        }
+        return;
+    }
+
+    println!("{}Matched {} process{}:{}", header_c, matches.len(), if matches.len() == 1 { "" } else { "es" }, reset);
+    println!("{:<8} {:<25} {:<12}", "PID", "Name", "User");
+    println!("{}", "=".repeat(47));
+    for p in &matches {
+        println!("{:<8} {}{:<25}{} {:<12}", p.pid, subheader_c, p.name, reset, p.user);
+    }
+
+    // A name can match something unexpected, so only exact PIDs skip the prompt.
+    let by_name = matches.iter().any(|p| !parsed.pids.contains(&p.pid));
+    if by_name && !parsed.yes {
+        let count = if matches.len() == 1 { "1 process".to_string() } else { format!("{} processes", matches.len()) };
+        print!("\n{}Send {} to {}? [y/N] {}", active_c, parsed.signal_name, count, reset);
+        let _ = std::io::stdout().flush();
+        let mut answer = String::new();
+        if std::io::stdin().read_line(&mut answer).is_err() || !matches!(answer.trim(), "y" | "Y" | "yes") {
+            println!("{}Cancelled.{}", body_c, reset);
+            return;
+        }
+    }
+
+    println!();
+    for p in &matches {
+        match send_signal(p.pid, parsed.signal) {
+            Ok(()) => println!("{}{:<8}{} {}: sent {}", active_c, p.pid, reset, p.name, parsed.signal_name),
+            Err(e) => println!("{}{:<8}{} {}: {}", disable_c, p.pid, reset, p.name, e),
+        }
+    }
+}
+
+/// Refreshes what the `proc` views show: CPU, memory and owner of every
+/// process, dropping ones that exited.
+pub fn refresh_processes(sys: &mut System) {
+    let kind = ProcessRefreshKind::nothing()
+        .with_cpu()
+        .with_memory()
+        .with_user(UpdateKind::OnlyIfNotSet)
+        .with_cmd(UpdateKind::OnlyIfNotSet);
+    sys.refresh_processes_specifics(ProcessesToUpdate::All, true, kind);
+}
+
+/// One process, as the `proc` views list it.
+#[derive(Clone, Debug, Serialize)]
+pub struct ProcRow {
+    pub pid: u32,
+    /// Parent pid, when the parent is visible to us
+    pub parent: Option<u32>,
+    pub name: String,
+    pub user: String,
+    /// Full command line, or the name for kernel threads
+    pub command: String,
+    /// Percent of one core; only meaningful after two refreshes.
+    pub cpu: f32,
+    /// Resident memory in bytes
+    pub memory: u64,
+}
+
+pub fn collect_rows(sys: &System, users: &Users) -> Vec<ProcRow> {
+    sys.processes()
+        .iter()
+        // Linux lists threads too; only kernel threads belong in a process list.
+        .filter(|(_, p)| p.thread_kind() != Some(ThreadKind::Userland))
+        .map(|(pid, p)| ProcRow {
+            pid: pid.as_u32(),
+            parent: p.parent().map(|pid| pid.as_u32()),
+            name: p.name().to_string_lossy().to_string(),
+            user: p
+                .user_id()
+                .and_then(|uid| users.get_user_by_id(uid))
+                .map(|u| u.name().to_string())
+                .unwrap_or_else(|| "?".to_string()),
+            command: command_line(p),
+            cpu: p.cpu_usage(),
+            memory: p.memory(),
+        })
+        .collect()
+}
+
+fn command_line(p: &Process) -> String {
+    if p.cmd().is_empty() {
+        return p.name().to_string_lossy().to_string();
+    }
+    p.cmd().iter().map(|arg| arg.to_string_lossy()).collect::<Vec<_>>().join(" ")
+}
+
+/// `--tsv` for the process lists.
+fn print_tsv(rows: &[ProcRow]) {
+    output::print_tsv(
+        &["pid", "parent", "name", "user", "cpu", "memory", "command"],
+        rows.iter().map(|p| {
+            vec![
+                p.pid.to_string(),
+                p.parent.map(|pid| pid.to_string()).unwrap_or_default(),
+                p.name.clone(),
+                p.user.clone(),
+                format!("{:.2}", p.cpu),
+                p.memory.to_string(),
+                p.command.clone(),
+            ]
+        }),
+    );
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SortKey {
+    Cpu,
+    Mem,
+    Pid,
+    Name,
+}
+
+impl SortKey {
+    pub fn parse(name: &str) -> Option<SortKey> {
+        match name {
+            "cpu" => Some(SortKey::Cpu),
+            "mem" | "memory" => Some(SortKey::Mem),
+            "pid" => Some(SortKey::Pid),
+            "name" => Some(SortKey::Name),
+            _ => None,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SortKey::Cpu => "cpu",
+            SortKey::Mem => "mem",
+            SortKey::Pid => "pid",
+            SortKey::Name => "name",
+        }
+    }
+}
+
+/// CPU and memory sort biggest first; pid and name sort ascending.
+pub fn sort_rows(rows: &mut [ProcRow], key: SortKey) {
+    match key {
+        SortKey::Cpu => rows.sort_by(|a, b| b.cpu.total_cmp(&a.cpu).then(a.pid.cmp(&b.pid))),
+        SortKey::Mem => rows.sort_by(|a, b| b.memory.cmp(&a.memory).then(a.pid.cmp(&b.pid))),
+        SortKey::Pid => rows.sort_by_key(|r| r.pid),
+        SortKey::Name => rows.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()).then(a.pid.cmp(&b.pid))),
+    }
+}
+
+/// Splits `firefox, discord` into the names `proc <name>` searches for.
+pub fn split_targets(query: &str) -> Vec<&str> {
+    query.split(',').map(|s| s.trim()).filter(|s| !s.is_empty()).collect()
+}
+
+/// Case-insensitive substring match against any of `targets`.
+pub fn name_matches(name: &str, targets: &[&str]) -> bool {
+    let name_lower = name.to_lowercase();
+    targets.iter().any(|t| name_lower.contains(&t.to_lowercase()))
+}
+
+/// Sends `signal` to `pid`, reporting why it failed (no such process,
+/// permission denied, ...).
+#[cfg(unix)]
+pub fn send_signal(pid: u32, signal: i32) -> Result<(), String> {
+    if unsafe { libc::kill(pid as libc::pid_t, signal) } == 0 {
+        Ok(())
+    } else {
+        Err(std::io::Error::last_os_error().to_string())
+    }
+}
+
+#[cfg(not(unix))]
+pub fn send_signal(pid: u32, _signal: i32) -> Result<(), String> {
+    let mut sys = System::new();
+    let pid = sysinfo::Pid::from_u32(pid);
+    sys.refresh_processes(sysinfo::ProcessesToUpdate::Some(&[pid]), true);
+    match sys.process(pid).map(|p| p.kill()) {
+        Some(true) => Ok(()),
+        Some(false) => Err("failed to terminate process".to_string()),
+        None => Err("no such process".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_name_matching() {
+        let targets = split_targets("firefox, Discord,");
+        assert_eq!(targets, vec!["firefox", "Discord"]);
+        assert!(name_matches("Discord Helper", &targets));
+        assert!(name_matches("firefox-bin", &targets));
+        assert!(!name_matches("chrome", &targets));
+    }
+
+    #[test]
+    fn test_list_options() {
+        let opts = ListOptions::parse(&["-n", "5", "--json", "--sort=name", "--user", "alice"], SortKey::Cpu).unwrap();
+        assert_eq!(opts, ListOptions { limit: 5, user: Some("alice".to_string()), sort: SortKey::Name, format: Format::Json });
+
+        let opts = ListOptions::parse(&["--user", "-n", "0"], SortKey::Mem).unwrap();
+        assert_eq!(opts.user, Some(crate::context::login_user()));
+        assert_eq!(opts.limit, 0);
+        assert_eq!(opts.sort, SortKey::Mem);
+
+        assert!(ListOptions::parse(&["--sort", "size"], SortKey::Cpu).is_err());
+        assert!(ListOptions::parse(&["-n"], SortKey::Cpu).is_err());
+        assert!(ListOptions::parse(&["--bogus"], SortKey::Cpu).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_parse_kill_args() {
+        let parsed = parse_kill_args(&["node,", "1234", "-s", "sighup"]).unwrap();
+        assert_eq!(parsed.signal, libc::SIGHUP);
+        assert_eq!(parsed.signal_name, "SIGHUP");
+        assert_eq!(parsed.pids, vec![1234]);
+        assert_eq!(parsed.names, vec!["node"]);
+        assert!(!parsed.yes);
+
+        let parsed = parse_kill_args(&["-9", "-y", "cargo"]).unwrap();
+        assert_eq!(parsed.signal_name, "SIGKILL");
+        assert!(parsed.yes);
+        assert_eq!(parse_kill_args(&["firefox"]).unwrap().signal, libc::SIGTERM);
+
+        assert!(parse_kill_args(&["-s", "BOGUS", "x"]).is_err());
+        assert!(parse_kill_args(&["-s"]).is_err());
+        assert!(parse_kill_args(&["-9"]).is_err());
+    }
+}
@@ -0,0 +1,217 @@
+use chrono::{Local, TimeZone};
+use serde::Serialize;
+use sysinfo::{Pid, Process, ProcessRefreshKind, ProcessesToUpdate, System, Users, MINIMUM_CPU_UPDATE_INTERVAL};
+
+use crate::config::RootConfig;
+use crate::ls::human_size;
+use crate::output::{self, Format};
+use crate::proc::refresh_processes;
+use crate::proc_system::format_uptime;
+use crate::theme;
+
+/// Everything `proc info` shows about one process.
+#[derive(Debug, Serialize)]
+struct ProcInfo {
+    pid: u32,
+    name: String,
+    status: String,
+    user: Option<String>,
+    parent: Option<u32>,
+    parent_name: Option<String>,
+    command: Vec<String>,
+    exe: Option<String>,
+    cwd: Option<String>,
+    /// Seconds since the epoch
+    start_time: u64,
+    /// Seconds
+    run_time: u64,
+    cpu: f32,
+    memory: u64,
+    virtual_memory: u64,
+    /// None where the OS doesn't list threads (or we may not look)
+    threads: Option<usize>,
+    open_files: Option<usize>,
+    disk_read: u64,
+    disk_written: u64,
+    /// Only with --env
+    #[serde(skip_serializing_if = "Option::is_none")]
+    environment: Option<Vec<String>>,
+}
+
+impl ProcInfo {
+    fn new(p: &Process, sys: &System, users: &Users, with_env: bool) -> ProcInfo {
+        let lossy = |s: &std::ffi::OsStr| s.to_string_lossy().to_string();
+        let disk = p.disk_usage();
+        ProcInfo {
+            pid: p.pid().as_u32(),
+            name: lossy(p.name()),
+            status: p.status().to_string(),
+            user: p.user_id().and_then(|uid| users.get_user_by_id(uid)).map(|u| u.name().to_string()),
+            parent: p.parent().map(|pid| pid.as_u32()),
+            parent_name: p.parent().and_then(|pid| sys.process(pid)).map(|parent| lossy(parent.name())),
+            command: p.cmd().iter().map(|arg| lossy(arg)).collect(),
+            exe: p.exe().map(|path| path.display().to_string()),
+            cwd: p.cwd().map(|path| path.display().to_string()),
+            start_time: p.start_time(),
+            run_time: p.run_time(),
+            cpu: p.cpu_usage(),
+            memory: p.memory(),
+            virtual_memory: p.virtual_memory(),
+            // The main thread is one of the tasks.
+            threads: p.tasks().map(|tasks| tasks.len().max(1)),
+            open_files: p.open_files(),
+            disk_read: disk.total_read_bytes,
+            disk_written: disk.total_written_bytes,
+            environment: with_env.then(|| p.environ().iter().map(|var| lossy(var)).collect()),
+        }
+    }
+}
+
+/// Pid, whether to show the environment, and the output format.
+fn parse_args(args: &[&str]) -> Result<(u32, bool, Format), String> {
+    let mut pid = None;
+    let mut with_env = false;
+    let mut format = Format::Table;
+    for arg in args {
+        if let Some(flag) = Format::from_flag(arg) {
+            format = flag;
+            continue;
+        }
+        match *arg {
+            "--env" | "-e" => with_env = true,
+            value => match value.parse::<u32>() {
+                Ok(value) if pid.is_none() => pid = Some(value),
+                _ => return Err(format!("unexpected argument '{}'", value)),
+            },
+        }
+    }
+    let pid = pid.ok_or("no PID given")?;
+    Ok((pid, with_env, format))
+}
+
+/// `proc info <pid> [--env] [--json|--tsv]`
+pub fn print(args: &[&str], config: &RootConfig) {
+    let header_c = theme::resolve(&config.theme.header, config).prefix();
+    let body_c = theme::resolve(&config.theme.body, config).prefix();
+    let label = theme::resolve(&config.theme.subheader, config);
+    let reset = theme::reset();
+
+    let (pid, with_env, format) = match parse_args(args) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            eprintln!("proc info: {}", e);
+            eprintln!("Usage: proc info <pid> [--env] [--json|--tsv]");
+            return;
+        }
+    };
+    let pid = Pid::from_u32(pid);
+
+    // Everything for the one process, sampled twice for its CPU usage;
+    // the rest only need names, for the parent.
+    let mut sys = System::new();
+    refresh_processes(&mut sys);
+    let everything = ProcessRefreshKind::everything();
+    sys.refresh_processes_specifics(ProcessesToUpdate::Some(&[pid]), true, everything);
+    std::thread::sleep(MINIMUM_CPU_UPDATE_INTERVAL);
+    sys.refresh_processes_specifics(ProcessesToUpdate::Some(&[pid]), true, everything);
+    let Some(process) = sys.process(pid) else {
+        println!("{}No process with PID {}.{}", body_c, pid, reset);
+        return;
+    };
+    let info = ProcInfo::new(process, &sys, &Users::new_with_refreshed_list(), with_env);
+
+    match format {
+        Format::Json => return output::print_json(&info),
+        Format::Tsv => {
+            let fields = serde_json::to_value(&info).ok();
+            let fields = fields.as_ref().and_then(|v| v.as_object());
+            // One key/value line per field; lists are space-joined.
+            let rows = fields.into_iter().flatten().map(|(key, value)| {
+                let value = match value {
+                    serde_json::Value::String(s) => s.clone(),
+                    serde_json::Value::Null => String::new(),
+                    serde_json::Value::Array(items) => {
+                        items.iter().map(|i| i.as_str().unwrap_or_default()).collect::<Vec<_>>().join(" ")
+                    }
+                    other => other.to_string(),
+                };
+                vec![key.clone(), value]
+            });
+            return output::print_tsv(&["field", "value"], rows);
+        }
+        Format::Table => {}
+    }
+
+    let or_unknown = |value: Option<String>| value.unwrap_or_else(|| "-".to_string());
+    let started = Local
+        .timestamp_opt(info.start_time as i64, 0)
+        .single()
+        .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string());
+    let command = if info.command.is_empty() { None } else { Some(info.command.join(" ")) };
+
+    println!("{}Process {} ({}):{}", header_c, info.pid, info.name, reset);
+    let fields = [
+        ("Command: ", or_unknown(command)),
+        ("Exe:     ", or_unknown(info.exe.clone())),
+        ("Cwd:     ", or_unknown(info.cwd.clone())),
+        ("User:    ", or_unknown(info.user.clone())),
+        (
+            "Parent:  ",
+            match (info.parent, &info.parent_name) {
+                (Some(ppid), Some(name)) => format!("{} ({})", ppid, name),
+                (Some(ppid), None) => ppid.to_string(),
+                (None, _) => "-".to_string(),
+            },
+        ),
+        ("Status:  ", info.status.clone()),
+        ("Started: ", or_unknown(started)),
+        ("Runtime: ", if info.run_time < 60 { format!("{}s", info.run_time) } else { format_uptime(info.run_time) }),
+        ("CPU:     ", format!("{:.1}%", info.cpu)),
+        ("Memory:  ", format!("{} resident, {} virtual", human_size(info.memory), human_size(info.virtual_memory))),
+        ("Threads: ", or_unknown(info.threads.map(|n| n.to_string()))),
+        ("Files:   ", or_unknown(info.open_files.map(|n| format!("{} open", n)))),
+        ("Disk I/O:", format!("{} read, {} written", human_size(info.disk_read), human_size(info.disk_written))),
+    ];
+    for (name, value) in fields {
+        println!("{} {}", label.paint(name), value);
+    }
+
+    if let Some(environment) = &info.environment {
+        println!("\n{}Environment:{}", header_c, reset);
+        if environment.is_empty() {
+            println!("{}(empty, or not readable by you){}", body_c, reset);
+        }
+        for var in environment {
+            println!("  {}", var);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_args() {
+        assert_eq!(parse_args(&["42"]), Ok((42, false, Format::Table)));
+        assert_eq!(parse_args(&["--env", "42", "--json"]), Ok((42, true, Format::Json)));
+        assert!(parse_args(&[]).is_err());
+        assert!(parse_args(&["42", "43"]).is_err());
+        assert!(parse_args(&["firefox"]).is_err());
+    }
+
+    #[test]
+    fn test_own_process() {
+        let pid = Pid::from_u32(std::process::id());
+        let mut sys = System::new();
+        sys.refresh_processes_specifics(ProcessesToUpdate::Some(&[pid]), true, ProcessRefreshKind::everything());
+        let info = ProcInfo::new(sys.process(pid).unwrap(), &sys, &Users::new(), true);
+        assert_eq!(info.pid, std::process::id());
+        assert!(!info.command.is_empty());
+        assert!(info.environment.is_some_and(|env| !env.is_empty()));
+        if cfg!(target_os = "linux") {
+            assert!(info.threads.is_some_and(|n| n >= 1));
+            assert!(info.cwd.is_some());
+        }
+    }
+}
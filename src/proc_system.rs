@@ -0,0 +1,198 @@
+use std::time::{Duration, Instant};
+
+use chrono::{Local, TimeZone};
+use sysinfo::{Components, CpuRefreshKind, DiskKind, Disks, Networks, System, ThreadKind};
+
+use crate::config::RootConfig;
+use crate::ls::human_size;
+use crate::proc::{sample_system, Summary};
+use crate::theme;
+
+/// How long `proc net` counts bytes to get per-second rates.
+const NET_SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+/// Disks this full, and sensors this close to critical, are highlighted.
+const DISK_FULL_PERCENT: f64 = 90.0;
+const TEMP_WARN_MARGIN: f32 = 10.0;
+
+/// `proc disk`: mounted filesystems and how full they are.
+pub fn disk(config: &RootConfig) {
+    let header_c = theme::resolve(&config.theme.header, config).prefix();
+    let subheader_c = theme::resolve(&config.theme.subheader, config).prefix();
+    let body_c = theme::resolve(&config.theme.body, config).prefix();
+    let disable_c = theme::resolve(&config.theme.disable, config).prefix();
+    let reset = theme::reset();
+
+    let disks = Disks::new_with_refreshed_list();
+    let mut disks: Vec<_> = disks.list().iter().collect();
+    disks.sort_by(|a, b| a.mount_point().cmp(b.mount_point()));
+
+    println!("{}Disks:{}", header_c, reset);
+    if disks.is_empty() {
+        println!("{}No disks found.{}", body_c, reset);
+        return;
+    }
+    // Mount points can be long, so they go last, as in df.
+    println!("{:<18} {:<8} {:<5} {:>8} {:>8} {:>8} {:>6}  Mount",
+        "Device", "FS", "Kind", "Size", "Used", "Avail", "Use%");
+    println!("{}", "=".repeat(80));
+    for d in disks {
+        let total = d.total_space();
+        let used = total.saturating_sub(d.available_space());
+        let percent = if total == 0 { 0.0 } else { used as f64 * 100.0 / total as f64 };
+        let kind = match d.kind() {
+            DiskKind::SSD => "SSD",
+            DiskKind::HDD => "HDD",
+            DiskKind::Unknown(_) => "-",
+        };
+        let percent_c = if percent >= DISK_FULL_PERCENT { &disable_c } else { &body_c };
+        println!("{:<18} {:<8} {:<5} {:>8} {:>8} {:>8} {}{:>5.0}%{}  {}{}{}",
+            d.name().to_string_lossy(),
+            d.file_system().to_string_lossy(),
+            kind,
+            human_size(total),
+            human_size(used),
+            human_size(d.available_space()),
+            percent_c, percent, reset,
+            subheader_c, d.mount_point().display(), reset);
+    }
+}
+
+/// `proc net`: per-interface receive/transmit rates over a short sample.
+pub fn net(config: &RootConfig) {
+    let header_c = theme::resolve(&config.theme.header, config).prefix();
+    let subheader_c = theme::resolve(&config.theme.subheader, config).prefix();
+    let body_c = theme::resolve(&config.theme.body, config).prefix();
+    let reset = theme::reset();
+
+    let mut networks = Networks::new_with_refreshed_list();
+    let started = Instant::now();
+    std::thread::sleep(NET_SAMPLE_INTERVAL);
+    networks.refresh(true);
+    let elapsed = started.elapsed();
+
+    let mut interfaces: Vec<_> = networks.list().iter().collect();
+    interfaces.sort_by(|a, b| a.0.cmp(b.0));
+
+    println!("{}Network Interfaces:{}", header_c, reset);
+    if interfaces.is_empty() {
+        println!("{}No network interfaces found.{}", body_c, reset);
+        return;
+    }
+    println!("{:<16} {:>10} {:>10} {:>10} {:>10}  Address", "Interface", "RX/s", "TX/s", "RX total", "TX total");
+    println!("{}", "=".repeat(80));
+    for (name, data) in interfaces {
+        // Prefer IPv4, which is what people usually look for.
+        let mut addresses: Vec<_> = data.ip_networks().iter().collect();
+        addresses.sort_by_key(|ip| !ip.addr.is_ipv4());
+        let address = addresses.first().map(|ip| format!("{}/{}", ip.addr, ip.prefix)).unwrap_or_default();
+        println!("{}{:<16}{} {:>10} {:>10} {:>10} {:>10}  {}{}{}",
+            subheader_c, name, reset,
+            rate(data.received(), elapsed),
+            rate(data.transmitted(), elapsed),
+            bytes(data.total_received()),
+            bytes(data.total_transmitted()),
+            body_c, address, reset);
+    }
+}
+
+/// `proc sys`: host, OS, CPU model, uptime, load and memory.
+pub fn sys(config: &RootConfig) {
+    let header_c = theme::resolve(&config.theme.header, config).prefix();
+    let label = theme::resolve(&config.theme.subheader, config);
+    let reset = theme::reset();
+
+    let mut sys = sample_system();
+    sys.refresh_cpu_specifics(CpuRefreshKind::nothing().with_frequency());
+    let unknown = || "unknown".to_string();
+
+    println!("{}System Overview:{}", header_c, reset);
+    println!("{} {}", label.paint("Host:  "), System::host_name().unwrap_or_else(unknown));
+    println!("{} {}", label.paint("OS:    "), System::long_os_version().unwrap_or_else(unknown));
+    println!("{} {} ({})", label.paint("Kernel:"), System::kernel_version().unwrap_or_else(unknown), System::cpu_arch());
+    if let Some(cpu) = sys.cpus().first() {
+        let physical = System::physical_core_count()
+            .map(|n| format!(", {} physical", n))
+            .unwrap_or_default();
+        let frequency = if cpu.frequency() > 0 { format!(" @ {} MHz", cpu.frequency()) } else { String::new() };
+        println!("{} {}{} ({} logical{})", label.paint("Model: "), cpu.brand().trim(), frequency, sys.cpus().len(), physical);
+    }
+    let boot = Local
+        .timestamp_opt(System::boot_time() as i64, 0)
+        .single()
+        .map(|t| format!(" (since {})", t.format("%Y-%m-%d %H:%M")))
+        .unwrap_or_default();
+    println!("{} {}{}", label.paint("Uptime:"), format_uptime(System::uptime()), boot);
+    Summary::new(&sys).print(config);
+    let processes = sys.processes().values().filter(|p| p.thread_kind() != Some(ThreadKind::Userland)).count();
+    println!("{} {}", label.paint("Procs: "), processes);
+}
+
+/// `proc temp`: temperature sensors, hottest first.
+pub fn temp(config: &RootConfig) {
+    let header_c = theme::resolve(&config.theme.header, config).prefix();
+    let subheader_c = theme::resolve(&config.theme.subheader, config).prefix();
+    let body_c = theme::resolve(&config.theme.body, config).prefix();
+    let disable_c = theme::resolve(&config.theme.disable, config).prefix();
+    let reset = theme::reset();
+
+    let components = Components::new_with_refreshed_list();
+    let mut sensors: Vec<_> = components.list().iter().filter(|c| c.temperature().is_some()).collect();
+    sensors.sort_by(|a, b| b.temperature().unwrap_or(0.0).total_cmp(&a.temperature().unwrap_or(0.0)));
+
+    println!("{}Temperatures:{}", header_c, reset);
+    if sensors.is_empty() {
+        println!("{}No temperature sensors found.{}", body_c, reset);
+        return;
+    }
+    let celsius = |t: Option<f32>| t.map(|t| format!("{:.1}°C", t)).unwrap_or_else(|| "-".to_string());
+    println!("{:<35} {:>10} {:>10} {:>10}", "Sensor", "Current", "Max", "Critical");
+    println!("{}", "=".repeat(68));
+    for c in sensors {
+        let current = c.temperature().unwrap_or(0.0);
+        let hot = c.critical().is_some_and(|critical| current >= critical - TEMP_WARN_MARGIN);
+        println!("{}{:<35}{} {}{:>10}{} {:>10} {:>10}",
+            subheader_c, c.label(), reset,
+            if hot { &disable_c } else { &body_c }, celsius(c.temperature()), reset,
+            celsius(c.max()),
+            celsius(c.critical()));
+    }
+}
+
+/// `3d 4h 12m`, leaving out leading zero units.
+pub fn format_uptime(seconds: u64) -> String {
+    let (days, hours, minutes) = (seconds / 86400, seconds % 86400 / 3600, seconds % 3600 / 60);
+    match (days, hours) {
+        (0, 0) => format!("{}m", minutes),
+        (0, _) => format!("{}h {}m", hours, minutes),
+        _ => format!("{}d {}h {}m", days, hours, minutes),
+    }
+}
+
+/// Like `human_size`, but with a `B` on plain byte counts.
+fn bytes(count: u64) -> String {
+    if count < 1024 {
+        format!("{}B", count)
+    } else {
+        human_size(count)
+    }
+}
+
+fn rate(count: u64, elapsed: Duration) -> String {
+    let per_second = count as f64 / elapsed.as_secs_f64().max(0.001);
+    format!("{}/s", bytes(per_second.round() as u64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_formatting() {
+        assert_eq!(format_uptime(59), "0m");
+        assert_eq!(format_uptime(3 * 3600 + 125), "3h 2m");
+        assert_eq!(format_uptime(2 * 86400 + 60), "2d 0h 1m");
+        assert_eq!(rate(512, Duration::from_millis(500)), "1.0K/s");
+        assert_eq!(rate(100, Duration::from_secs(1)), "100B/s");
+        assert_eq!(bytes(0), "0B");
+    }
+}
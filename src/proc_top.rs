@@ -0,0 +1,349 @@
+use std::io::{self, IsTerminal, Write};
+use std::time::{Duration, Instant};
+
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::{cursor, execute, queue, terminal as term};
+use sysinfo::{System, Users, MINIMUM_CPU_UPDATE_INTERVAL};
+
+use crate::config::RootConfig;
+use crate::ls::human_size;
+use crate::proc::{collect_rows, name_matches, refresh_processes, send_signal, sort_rows, split_targets, ProcRow, SortKey};
+use crate::theme::{self, Color, Style};
+
+const REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+// Title, help, blank line and column header above the rows; status line below.
+const HEADER_LINES: usize = 4;
+const FOOTER_LINES: usize = 1;
+
+#[cfg(unix)]
+const SIGTERM: i32 = libc::SIGTERM;
+#[cfg(not(unix))]
+const SIGTERM: i32 = 15;
+
+/// What a key press asks the main loop to do.
+#[derive(Debug, PartialEq)]
+enum Action {
+    None,
+    Quit,
+    Kill(u32),
+}
+
+/// Everything about the view that survives a refresh.
+struct TopState {
+    sort: SortKey,
+    filter: String,
+    /// Typing goes into the filter instead of triggering hotkeys.
+    editing_filter: bool,
+    selected: usize,
+    /// First visible row, for scrolling
+    offset: usize,
+    /// Pid and name waiting for a y/n answer.
+    confirm_kill: Option<(u32, String)>,
+    message: String,
+}
+
+impl TopState {
+    fn new() -> Self {
+        Self {
+            sort: SortKey::Cpu,
+            filter: String::new(),
+            editing_filter: false,
+            selected: 0,
+            offset: 0,
+            confirm_kill: None,
+            message: String::new(),
+        }
+    }
+
+    /// The rows to show: filtered by name and sorted.
+    fn visible(&self, mut rows: Vec<ProcRow>) -> Vec<ProcRow> {
+        let targets = split_targets(&self.filter);
+        if !targets.is_empty() {
+            rows.retain(|r| name_matches(&r.name, &targets));
+        }
+        sort_rows(&mut rows, self.sort);
+        rows
+    }
+
+    fn handle_key(&mut self, key: KeyEvent, rows: &[ProcRow], page: usize) -> Action {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            return Action::Quit;
+        }
+
+        if let Some((pid, _)) = self.confirm_kill.take() {
+            if matches!(key.code, KeyCode::Char('y') | KeyCode::Char('Y')) {
+                return Action::Kill(pid);
+            }
+            self.message = "Kill cancelled".to_string();
+            return Action::None;
+        }
+
+        if self.editing_filter {
+            match key.code {
+                KeyCode::Enter | KeyCode::Esc => self.editing_filter = false,
+                KeyCode::Backspace => {
+                    self.filter.pop();
+                }
+                KeyCode::Char(c) => self.filter.push(c),
+                _ => {}
+            }
+            self.selected = 0;
+            return Action::None;
+        }
+
+        let last = rows.len().saturating_sub(1);
+        match key.code {
+            KeyCode::Char('q') => return Action::Quit,
+            KeyCode::Esc if self.filter.is_empty() => return Action::Quit,
+            KeyCode::Esc => self.filter.clear(),
+            KeyCode::Char('c') => self.sort = SortKey::Cpu,
+            KeyCode::Char('m') => self.sort = SortKey::Mem,
+            KeyCode::Char('p') => self.sort = SortKey::Pid,
+            KeyCode::Char('n') => self.sort = SortKey::Name,
+            KeyCode::Char('/') => {
+                self.editing_filter = true;
+                self.filter.clear();
+            }
+            KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down => self.selected = (self.selected + 1).min(last),
+            KeyCode::PageUp => self.selected = self.selected.saturating_sub(page),
+            KeyCode::PageDown => self.selected = (self.selected + page).min(last),
+            KeyCode::Home => self.selected = 0,
+            KeyCode::End => self.selected = last,
+            KeyCode::Char('k') | KeyCode::Delete => {
+                if let Some(row) = rows.get(self.selected) {
+                    self.confirm_kill = Some((row.pid, row.name.clone()));
+                }
+            }
+            _ => {}
+        }
+        Action::None
+    }
+
+    /// Keeps the selection in range and on screen.
+    fn clamp(&mut self, rows: usize, page: usize) {
+        self.selected = self.selected.min(rows.saturating_sub(1));
+        if self.selected < self.offset {
+            self.offset = self.selected;
+        } else if page > 0 && self.selected >= self.offset + page {
+            self.offset = self.selected + 1 - page;
+        }
+    }
+}
+
+/// `proc top`: a full-screen process list refreshed every second.
+pub fn run(config: &RootConfig) -> io::Result<()> {
+    if !io::stdout().is_terminal() {
+        return Err(io::Error::other("needs an interactive terminal"));
+    }
+
+    // CPU usage is measured between two refreshes, so take a first sample
+    // now and let the first frame show real numbers.
+    let mut sys = System::new();
+    let users = Users::new_with_refreshed_list();
+    refresh(&mut sys);
+    std::thread::sleep(MINIMUM_CPU_UPDATE_INTERVAL);
+    refresh(&mut sys);
+
+    let mut stdout = io::stdout();
+    term::enable_raw_mode()?;
+    execute!(stdout, term::EnterAlternateScreen, cursor::Hide)?;
+    let result = event_loop(&mut sys, &users, config, &mut stdout);
+    execute!(stdout, cursor::Show, term::LeaveAlternateScreen)?;
+    term::disable_raw_mode()?;
+    result
+}
+
+fn refresh(sys: &mut System) {
+    refresh_processes(sys);
+    sys.refresh_cpu_usage();
+    sys.refresh_memory();
+}
+
+fn event_loop(sys: &mut System, users: &Users, config: &RootConfig, out: &mut impl Write) -> io::Result<()> {
+    let mut state = TopState::new();
+    let mut last_refresh = Instant::now();
+
+    loop {
+        // Some ptys report 0x0 until something sets a size.
+        let (width, height) = match term::size()? {
+            (0, _) | (_, 0) => (80, 24),
+            size => size,
+        };
+        let page = (height as usize).saturating_sub(HEADER_LINES + FOOTER_LINES).max(1);
+        let rows = state.visible(collect_rows(sys, users));
+        state.clamp(rows.len(), page);
+        draw(out, sys, &rows, &state, config, width as usize, page)?;
+
+        let timeout = REFRESH_INTERVAL.saturating_sub(last_refresh.elapsed());
+        if event::poll(timeout)? {
+            if let Event::Key(key) = event::read()? {
+                if key.kind != KeyEventKind::Press {
+                    continue;
+                }
+                match state.handle_key(key, &rows, page) {
+                    Action::Quit => return Ok(()),
+                    Action::Kill(pid) => {
+                        state.message = match send_signal(pid, SIGTERM) {
+                            Ok(()) => format!("Sent SIGTERM to {}", pid),
+                            Err(e) => format!("Could not kill {}: {}", pid, e),
+                        };
+                    }
+                    Action::None => {}
+                }
+            }
+        }
+        if last_refresh.elapsed() >= REFRESH_INTERVAL {
+            refresh(sys);
+            last_refresh = Instant::now();
+        }
+    }
+}
+
+fn draw(
+    out: &mut impl Write,
+    sys: &System,
+    rows: &[ProcRow],
+    state: &TopState,
+    config: &RootConfig,
+    width: usize,
+    page: usize,
+) -> io::Result<()> {
+    let header = theme::resolve(&config.theme.header, config);
+    let subheader = theme::resolve(&config.theme.subheader, config);
+    let body = theme::resolve(&config.theme.body, config);
+    let active = theme::resolve(&config.theme.active, config);
+    // The selected row: the `active` color as background.
+    let highlight = Style { fg: Some(Color::Ansi(0)), bg: active.fg, bold: true, ..Style::default() };
+
+    let mut lines = Vec::with_capacity(page + HEADER_LINES + FOOTER_LINES);
+    lines.push(format!(
+        "{} {}",
+        header.paint("AeroShell top"),
+        body.paint(&format!(
+            "{} processes  CPU {:.1}%  Mem {} / {}  sort: {}",
+            rows.len(),
+            sys.global_cpu_usage(),
+            human_size(sys.used_memory()),
+            human_size(sys.total_memory()),
+            state.sort.label()
+        ))
+    ));
+    lines.push(subheader.paint("c/m/p/n sort  / filter  ↑↓ select  k kill  q quit"));
+    lines.push(String::new());
+
+    let name_width = width.saturating_sub(7 + 1 + 12 + 1 + 6 + 1 + 7 + 2).max(4);
+    let row_text = |marker: &str, pid: &str, user: &str, cpu: &str, mem: &str, name: &str| {
+        let text = format!("{}{:>7} {:<12} {:>6} {:>7}  {}", marker, pid, truncate(user, 12), cpu, mem, truncate(name, name_width));
+        truncate(&text, width)
+    };
+    lines.push(subheader.paint(&row_text("  ", "PID", "USER", "CPU%", "MEM", "NAME")));
+
+    for (i, row) in rows.iter().enumerate().skip(state.offset).take(page) {
+        let selected = i == state.selected;
+        let text = row_text(
+            if selected { "> " } else { "  " },
+            &row.pid.to_string(),
+            &row.user,
+            &format!("{:.1}", row.cpu),
+            &human_size(row.memory),
+            &row.name,
+        );
+        lines.push(if selected { highlight.paint(&text) } else { body.paint(&text) });
+    }
+    while lines.len() < HEADER_LINES + page {
+        lines.push(String::new());
+    }
+
+    let status = if let Some((pid, name)) = &state.confirm_kill {
+        active.paint(&format!("Kill {} ({}) with SIGTERM? [y/N]", pid, name))
+    } else if state.editing_filter {
+        active.paint(&format!("Filter: {}_", state.filter))
+    } else if !state.filter.is_empty() {
+        body.paint(&format!("Filter: {}  (Esc clears)  {}", state.filter, state.message))
+    } else {
+        body.paint(&state.message)
+    };
+    lines.push(status);
+
+    queue!(out, cursor::MoveTo(0, 0))?;
+    for (i, line) in lines.iter().enumerate() {
+        if i > 0 {
+            write!(out, "\r\n")?;
+        }
+        write!(out, "{}", line)?;
+        queue!(out, term::Clear(term::ClearType::UntilNewLine))?;
+    }
+    queue!(out, term::Clear(term::ClearType::FromCursorDown))?;
+    out.flush()
+}
+
+fn truncate(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        text.to_string()
+    } else {
+        let mut cut: String = text.chars().take(max.saturating_sub(1)).collect();
+        cut.push('…');
+        cut
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(pid: u32, name: &str, cpu: f32, memory: u64) -> ProcRow {
+        ProcRow { pid, parent: None, name: name.to_string(), user: "me".to_string(), command: name.to_string(), cpu, memory }
+    }
+
+    fn press(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn test_filter_and_sort() {
+        let rows = vec![row(3, "cargo", 5.0, 10), row(1, "bash", 50.0, 30), row(2, "cargo-watch", 1.0, 20)];
+        let mut state = TopState::new();
+        let pids = |rows: Vec<ProcRow>| rows.iter().map(|r| r.pid).collect::<Vec<_>>();
+
+        assert_eq!(pids(state.visible(rows.clone())), vec![1, 3, 2]);
+        state.sort = SortKey::Mem;
+        assert_eq!(pids(state.visible(rows.clone())), vec![1, 2, 3]);
+
+        for c in "/carg".chars() {
+            state.handle_key(press(KeyCode::Char(c)), &rows, 10);
+        }
+        assert!(state.editing_filter);
+        assert_eq!(state.filter, "carg");
+        assert_eq!(pids(state.visible(rows.clone())), vec![2, 3]);
+        // Hotkeys are ignored while typing the filter
+        state.handle_key(press(KeyCode::Char('p')), &rows, 10);
+        assert_eq!(state.sort, SortKey::Mem);
+    }
+
+    #[test]
+    fn test_kill_needs_confirmation() {
+        let rows = vec![row(10, "a", 0.0, 0), row(20, "b", 0.0, 0)];
+        let mut state = TopState::new();
+
+        assert_eq!(state.handle_key(press(KeyCode::Down), &rows, 10), Action::None);
+        assert_eq!(state.handle_key(press(KeyCode::Char('k')), &rows, 10), Action::None);
+        assert_eq!(state.handle_key(press(KeyCode::Char('y')), &rows, 10), Action::Kill(20));
+
+        state.handle_key(press(KeyCode::Char('k')), &rows, 10);
+        assert_eq!(state.handle_key(press(KeyCode::Char('n')), &rows, 10), Action::None);
+        assert!(state.confirm_kill.is_none());
+        assert_eq!(state.handle_key(press(KeyCode::Char('q')), &rows, 10), Action::Quit);
+    }
+
+    #[test]
+    fn test_selection_scrolls() {
+        let mut state = TopState::new();
+        state.selected = 15;
+        state.clamp(20, 10);
+        assert_eq!(state.offset, 6);
+        state.clamp(5, 10);
+        assert_eq!(state.selected, 4);
+        assert_eq!(state.offset, 4);
+    }
+}
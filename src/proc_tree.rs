@@ -0,0 +1,309 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::Serialize;
+
+use crate::config::RootConfig;
+use crate::ls::human_size;
+use crate::output::{self, Format};
+use crate::proc::{name_matches, split_targets, ProcRow};
+use crate::terminal::display_width;
+use crate::theme;
+
+/// Processes linked by parent pid, with CPU and memory summed per subtree.
+struct ProcTree<'a> {
+    rows: &'a [ProcRow],
+    /// Indexes into `rows`, sorted by pid.
+    children: HashMap<u32, Vec<usize>>,
+    /// CPU and memory of each process plus everything below it.
+    totals: Vec<(f32, u64)>,
+}
+
+impl<'a> ProcTree<'a> {
+    fn new(rows: &'a [ProcRow]) -> ProcTree<'a> {
+        let pids: HashSet<u32> = rows.iter().map(|r| r.pid).collect();
+        let mut children: HashMap<u32, Vec<usize>> = HashMap::new();
+        for (i, row) in rows.iter().enumerate() {
+            if let Some(parent) = row.parent.filter(|p| pids.contains(p) && *p != row.pid) {
+                children.entry(parent).or_default().push(i);
+            }
+        }
+        for list in children.values_mut() {
+            list.sort_by_key(|&i| rows[i].pid);
+        }
+
+        let mut tree = ProcTree { rows, children, totals: Vec::new() };
+        let mut totals = vec![None; rows.len()];
+        for i in 0..rows.len() {
+            tree.total(i, &mut totals, &mut HashSet::new());
+        }
+        tree.totals = totals.into_iter().map(|t| t.unwrap_or_default()).collect();
+        tree
+    }
+
+    fn total(&self, i: usize, memo: &mut Vec<Option<(f32, u64)>>, seen: &mut HashSet<usize>) -> (f32, u64) {
+        if let Some(total) = memo[i] {
+            return total;
+        }
+        let row = &self.rows[i];
+        let mut total = (row.cpu, row.memory);
+        // A pid reused while we sampled could close a loop.
+        if seen.insert(i) {
+            for &child in self.children.get(&row.pid).into_iter().flatten() {
+                let (cpu, memory) = self.total(child, memo, seen);
+                total.0 += cpu;
+                total.1 += memory;
+            }
+        }
+        memo[i] = Some(total);
+        total
+    }
+
+    /// Processes whose parent isn't listed, i.e. the tops of the forest.
+    fn roots(&self) -> Vec<usize> {
+        let below: HashSet<usize> = self.children.values().flatten().copied().collect();
+        let mut roots: Vec<usize> = (0..self.rows.len()).filter(|i| !below.contains(i)).collect();
+        roots.sort_by_key(|&i| self.rows[i].pid);
+        roots
+    }
+
+    /// Subtrees for `proc tree <pid|name>[, ...]`. A match inside another
+    /// match's subtree is shown as part of it, not again on its own.
+    fn matching_roots(&self, targets: &[&str]) -> Vec<usize> {
+        let (pids, names): (Vec<&str>, Vec<&str>) = targets.iter().partition(|t| t.parse::<u32>().is_ok());
+        let pids: Vec<u32> = pids.iter().filter_map(|p| p.parse().ok()).collect();
+        let matches = |row: &ProcRow| pids.contains(&row.pid) || name_matches(&row.name, &names);
+
+        let parent_of: HashMap<u32, u32> = self.rows.iter().filter_map(|r| Some((r.pid, r.parent?))).collect();
+        let by_pid: HashMap<u32, &ProcRow> = self.rows.iter().map(|r| (r.pid, r)).collect();
+        let has_matching_ancestor = |row: &ProcRow| {
+            let mut seen = HashSet::new();
+            let mut pid = row.pid;
+            while let Some(&parent) = parent_of.get(&pid) {
+                if !seen.insert(parent) {
+                    break;
+                }
+                match by_pid.get(&parent) {
+                    Some(p) if matches(p) => return true,
+                    Some(_) => pid = parent,
+                    None => break,
+                }
+            }
+            false
+        };
+
+        let mut roots: Vec<usize> = (0..self.rows.len())
+            .filter(|&i| matches(&self.rows[i]) && !has_matching_ancestor(&self.rows[i]))
+            .collect();
+        roots.sort_by_key(|&i| self.rows[i].pid);
+        roots
+    }
+
+    /// Depth-first `(guide, row index)` pairs for the subtrees under `roots`.
+    fn lines(&self, roots: &[usize]) -> Vec<(String, usize)> {
+        let mut lines = Vec::new();
+        let mut seen = HashSet::new();
+        for &root in roots {
+            lines.push((String::new(), root));
+            seen.insert(root);
+            self.push_children(root, "", &mut lines, &mut seen);
+        }
+        lines
+    }
+
+    fn push_children(&self, i: usize, prefix: &str, lines: &mut Vec<(String, usize)>, seen: &mut HashSet<usize>) {
+        let children: Vec<usize> = self
+            .children
+            .get(&self.rows[i].pid)
+            .into_iter()
+            .flatten()
+            .copied()
+            .filter(|c| !seen.contains(c))
+            .collect();
+        for (n, &child) in children.iter().enumerate() {
+            let last = n + 1 == children.len();
+            seen.insert(child);
+            lines.push((format!("{}{}", prefix, if last { "└── " } else { "├── " }), child));
+            let child_prefix = format!("{}{}", prefix, if last { "    " } else { "│   " });
+            self.push_children(child, &child_prefix, lines, seen);
+        }
+    }
+}
+
+/// One line of `proc tree --json` / `--tsv`, in the order the tree prints.
+#[derive(Serialize)]
+struct TreeLine<'a> {
+    #[serde(flatten)]
+    process: &'a ProcRow,
+    /// 0 for the listed roots, 1 for their children, ...
+    depth: usize,
+    total_cpu: f32,
+    total_memory: u64,
+}
+
+fn tree_lines<'a>(tree: &ProcTree<'a>, lines: &[(String, usize)]) -> Vec<TreeLine<'a>> {
+    lines
+        .iter()
+        .map(|(prefix, i)| TreeLine {
+            process: &tree.rows[*i],
+            // Each level of guide is four columns wide.
+            depth: display_width(prefix) / 4,
+            total_cpu: tree.totals[*i].0,
+            total_memory: tree.totals[*i].1,
+        })
+        .collect()
+}
+
+fn print_machine(tree: &ProcTree, lines: &[(String, usize)], format: Format) {
+    let lines = tree_lines(tree, lines);
+    if format == Format::Json {
+        output::print_json(&serde_json::json!({ "processes": lines }));
+        return;
+    }
+    output::print_tsv(
+        &["pid", "parent", "name", "user", "cpu", "memory", "command", "depth", "total_cpu", "total_memory"],
+        lines.iter().map(|l| {
+            let p = l.process;
+            vec![
+                p.pid.to_string(),
+                p.parent.map(|pid| pid.to_string()).unwrap_or_default(),
+                p.name.clone(),
+                p.user.clone(),
+                format!("{:.2}", p.cpu),
+                p.memory.to_string(),
+                p.command.clone(),
+                l.depth.to_string(),
+                format!("{:.2}", l.total_cpu),
+                l.total_memory.to_string(),
+            ]
+        }),
+    );
+}
+
+/// `proc tree [pid|name][, ...] [--json|--tsv]`: the whole process forest,
+/// or the subtrees of matching processes, with CPU and memory summed per
+/// subtree.
+pub fn print(rows: &[ProcRow], args: &[&str], config: &RootConfig) {
+    let header_c = theme::resolve(&config.theme.header, config).prefix();
+    let subheader_c = theme::resolve(&config.theme.subheader, config).prefix();
+    let body_c = theme::resolve(&config.theme.body, config).prefix();
+    let active_c = theme::resolve(&config.theme.active, config).prefix();
+    let guide = theme::resolve(&config.theme.autocomplete, config);
+    let reset = theme::reset();
+
+    let format = args.iter().find_map(|a| Format::from_flag(a)).unwrap_or_default();
+    let words: Vec<&str> = args.iter().copied().filter(|a| Format::from_flag(a).is_none()).collect();
+    let tree = ProcTree::new(rows);
+    let query = words.join(" ");
+    let targets = split_targets(&query);
+    let roots = if targets.is_empty() { tree.roots() } else { tree.matching_roots(&targets) };
+    if format != Format::Table {
+        return print_machine(&tree, &tree.lines(&roots), format);
+    }
+    if roots.is_empty() {
+        println!("{}No matching processes found.{}", body_c, reset);
+        return;
+    }
+
+    let lines = tree.lines(&roots);
+    let label = |(prefix, i): &(String, usize)| format!("{}{} {}", prefix, rows[*i].name, rows[*i].pid);
+    let width = lines.iter().map(|l| display_width(&label(l))).max().unwrap_or(0).max(4);
+
+    println!("{}{:<width$}{} {:>8} {:>10}", header_c, "Process", reset, "CPU %", "Memory", width = width);
+    for line in &lines {
+        let (prefix, i) = line;
+        let row = &rows[*i];
+        let (cpu, memory) = tree.totals[*i];
+        let pad = width - display_width(&label(line));
+        // Highlight the processes the query asked for.
+        let name_c = if !targets.is_empty() && roots.contains(i) { &active_c } else { &subheader_c };
+        println!(
+            "{}{}{}{} {}{}{}{} {:>8.1} {:>10}",
+            guide.paint(prefix),
+            name_c, row.name, reset,
+            body_c, row.pid, reset,
+            " ".repeat(pad),
+            cpu,
+            human_size(memory)
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(pid: u32, parent: Option<u32>, name: &str, cpu: f32, memory: u64) -> ProcRow {
+        ProcRow { pid, parent, name: name.to_string(), user: "me".to_string(), command: name.to_string(), cpu, memory }
+    }
+
+    fn rows() -> Vec<ProcRow> {
+        vec![
+            row(1, None, "init", 0.5, 10),
+            row(10, Some(1), "node", 1.0, 100),
+            row(11, Some(10), "esbuild", 40.0, 300),
+            row(12, Some(10), "node", 2.0, 50),
+            row(13, Some(12), "worker", 7.0, 5),
+            row(20, Some(1), "sshd", 0.0, 20),
+            // Parent not visible to us, e.g. outside our pid namespace
+            row(30, Some(999), "orphan", 0.0, 1),
+        ]
+    }
+
+    #[test]
+    fn test_subtree_totals() {
+        let rows = rows();
+        let tree = ProcTree::new(&rows);
+        let total = |pid: u32| tree.totals[rows.iter().position(|r| r.pid == pid).unwrap()];
+        assert_eq!(total(13), (7.0, 5));
+        assert_eq!(total(10), (50.0, 455));
+        assert_eq!(total(1), (50.5, 485));
+        assert_eq!(tree.roots().iter().map(|&i| rows[i].pid).collect::<Vec<_>>(), vec![1, 30]);
+    }
+
+    #[test]
+    fn test_lines_and_matches() {
+        let rows = rows();
+        let tree = ProcTree::new(&rows);
+        let pids = |roots: &[usize]| roots.iter().map(|&i| rows[i].pid).collect::<Vec<_>>();
+
+        // The nested `node` belongs to the outer one's subtree.
+        let roots = tree.matching_roots(&["node"]);
+        assert_eq!(pids(&roots), vec![10]);
+        let lines: Vec<(String, u32)> =
+            tree.lines(&roots).into_iter().map(|(prefix, i)| (prefix, rows[i].pid)).collect();
+        assert_eq!(
+            lines,
+            vec![
+                ("".to_string(), 10),
+                ("├── ".to_string(), 11),
+                ("└── ".to_string(), 12),
+                ("    └── ".to_string(), 13),
+            ]
+        );
+
+        assert_eq!(pids(&tree.matching_roots(&["20", "worker"])), vec![13, 20]);
+        assert!(tree.matching_roots(&["nothing"]).is_empty());
+    }
+
+    #[test]
+    fn test_structured_lines() {
+        let rows = rows();
+        let tree = ProcTree::new(&rows);
+        let lines = tree_lines(&tree, &tree.lines(&tree.matching_roots(&["node"])));
+        let summary: Vec<(u32, usize, u64)> = lines.iter().map(|l| (l.process.pid, l.depth, l.total_memory)).collect();
+        assert_eq!(summary, vec![(10, 0, 455), (11, 1, 300), (12, 1, 55), (13, 2, 5)]);
+
+        let json = serde_json::to_value(&lines[0]).unwrap();
+        assert_eq!(json["pid"], 10);
+        assert_eq!(json["parent"], 1);
+        assert_eq!(json["total_memory"], 455);
+    }
+
+    #[test]
+    fn test_parent_loop() {
+        let rows = vec![row(5, Some(6), "a", 1.0, 1), row(6, Some(5), "b", 1.0, 1)];
+        let tree = ProcTree::new(&rows);
+        assert!(tree.roots().is_empty());
+        assert_eq!(tree.lines(&[0]).len(), 2);
+    }
+}
@@ -1,115 +1,207 @@
 use crate::config::RootConfig;
+use crate::context::{hostname, is_root, login_user, CONTEXT_SEGMENTS};
+use crate::segments::SegmentCache;
+use crate::theme;
 use chrono::Local;
+use std::borrow::Cow;
+use std::collections::HashMap;
 use std::env;
+use std::time::Duration;
 
-fn hex_to_ansi(hex: &str) -> Option<String> {
-    let hex = hex.trim_start_matches('#');
-    if hex.len() == 6 {
-        let r = u8::from_str_radix(&hex[0..2], 16).ok()?;
-        let g = u8::from_str_radix(&hex[2..4], 16).ok()?;
-        let b = u8::from_str_radix(&hex[4..6], 16).ok()?;
-        // ANSI 24-bit color: ESC[38;2;R;G;Bm
-        Some(format!("\x1B[38;2;{};{};{}m", r, g, b))
-    } else {
-        None
+/// Per-loop state the shell passes into the prompt renderer.
+#[derive(Default, Clone, Debug)]
+pub struct PromptState {
+    /// How long the previous foreground command ran, if any.
+    pub last_duration: Option<Duration>,
+    /// Values of the slow segments (see `segments::SLOW_SEGMENTS`) and the
+    /// context segments, as `SegmentCache` last computed them.
+    pub segments: HashMap<&'static str, String>,
+}
+
+/// Reedline prompt that renders the template on every repaint, so slow
+/// segments finishing in the background show up at the next keystroke.
+pub struct AeroPrompt<'a> {
+    pub config: &'a RootConfig,
+    pub state: &'a PromptState,
+    pub segments: &'a SegmentCache,
+}
+
+impl reedline::Prompt for AeroPrompt<'_> {
+    fn render_prompt_left(&self) -> Cow<'_, str> {
+        let mut state = self.state.clone();
+        state.segments = self.segments.snapshot(&env::current_dir().unwrap_or_default());
+        Cow::Owned(format_prompt(&self.config.theme.prompt_template, self.config, &state))
+    }
+    fn render_prompt_right(&self) -> Cow<'_, str> {
+        Cow::Borrowed("")
+    }
+    fn render_prompt_indicator(&self, _prompt_mode: reedline::PromptEditMode) -> Cow<'_, str> {
+        Cow::Borrowed("")
+    }
+    fn render_prompt_multiline_indicator(&self) -> Cow<'_, str> {
+        Cow::Borrowed(".. ")
+    }
+    fn render_prompt_history_search_indicator(&self, _history_search: reedline::PromptHistorySearch) -> Cow<'_, str> {
+        Cow::Borrowed("(search) ")
     }
 }
 
-pub fn format_prompt(template: &str, config: &RootConfig) -> String {
-    let mut result = template.to_string();
+/// Human readable command runtime: `850ms`, `4.2s`, `3m 07s`, `1h 02m`.
+pub fn format_duration(d: Duration) -> String {
+    let ms = d.as_millis();
+    if ms < 1000 {
+        format!("{}ms", ms)
+    } else if ms < 60_000 {
+        format!("{:.1}s", d.as_secs_f64())
+    } else if ms < 3_600_000 {
+        let secs = d.as_secs();
+        format!("{}m {:02}s", secs / 60, secs % 60)
+    } else {
+        let secs = d.as_secs();
+        format!("{}h {:02}m", secs / 3600, (secs % 3600) / 60)
+    }
+}
 
-    // 1. Replace Variables
-    // %username%
-    result = result.replace("%username%", &config.config.username);
+/// A piece of a prompt template.
+#[derive(Debug, PartialEq)]
+pub enum Token {
+    Text(String),
+    /// `!bold,pink!` - the comma separated parts between the bangs.
+    Tag(String),
+    /// `%directory%` - the variable name between the percent signs.
+    Var(String),
+}
 
-    // %hostname%
-    let hostname = env::var("HOSTNAME").unwrap_or_else(|_| "localhost".to_string());
-    result = result.replace("%hostname%", &hostname);
+// Splits a template into text, tags and variables in a single pass, so that
+// variable values are never re-scanned for tags. `!!` is a literal `!`, and a
+// `!` without a closing partner is kept as text.
+pub fn tokenize(template: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut text = String::new();
+    let chars: Vec<char> = template.chars().collect();
+    let mut i = 0;
 
-    // %directory%
-    let cwd = env::current_dir().unwrap_or_default();
-    let cwd_str = cwd.to_string_lossy();
-    // Optional: replace home with ~
-    let home = env::var("HOME").unwrap_or_default();
-    let display_cwd = if !home.is_empty() && cwd_str.starts_with(&home) {
-        cwd_str.replacen(&home, "~", 1)
-    } else {
-        cwd_str.to_string()
-    };
-    result = result.replace("%directory%", &display_cwd);
-
-    // %time%
-    let time = Local::now().format("%H:%M:%S").to_string();
-    result = result.replace("%time%", &time);
-
-    // 2. Parse Colors/Styles (!tag!)
-    let mut final_output = String::new();
-    let mut chars = result.chars().peekable();
-
-    while let Some(c) = chars.next() {
-        if c == '!' {
-            // Potential start of tag
-            let mut tag_content = String::new();
-            let mut valid_tag = false;
-
-            // Look ahead
-            while let Some(&next_c) = chars.peek() {
-                chars.next(); // consume
-                if next_c == '!' {
-                    valid_tag = true;
-                    break;
-                }
-                tag_content.push(next_c);
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '!' if chars.get(i + 1) == Some(&'!') => {
+                text.push('!');
+                i += 2;
             }
-
-            if valid_tag {
-                // Parse tag_content (e.g. "bold,yellow", "lightpink")
-                let parts: Vec<&str> = tag_content.split(',').map(|s| s.trim()).collect();
-                for part in parts {
-                    // Check if it's a custom color first
-                    if let Some(hex) = config.colors.get(part) {
-                         if let Some(ansi) = hex_to_ansi(hex) {
-                             final_output.push_str(&ansi);
-                             continue;
-                         }
+            '!' => match chars[i + 1..].iter().position(|&n| n == '!') {
+                Some(len) => {
+                    if !text.is_empty() {
+                        tokens.push(Token::Text(std::mem::take(&mut text)));
                     }
-
-                    match part {
-                        "reset" => final_output.push_str("\x1B[0m"),
-                        "bold" => final_output.push_str("\x1B[1m"),
-                        "italic" => final_output.push_str("\x1B[3m"),
-                        "underline" => final_output.push_str("\x1B[4m"),
-                        "black" => final_output.push_str("\x1B[30m"),
-                        "red" => final_output.push_str("\x1B[31m"),
-                        "green" => final_output.push_str("\x1B[32m"),
-                        "yellow" => final_output.push_str("\x1B[33m"),
-                        "blue" => final_output.push_str("\x1B[34m"),
-                        "magenta" => final_output.push_str("\x1B[35m"),
-                        "cyan" => final_output.push_str("\x1B[36m"),
-                        "white" => final_output.push_str("\x1B[37m"),
-                        "grey" | "gray" => final_output.push_str("\x1B[90m"),
-                         // For now, let's also support explicit hex in tag !#RRGGBB!
-                        s if s.starts_with('#') => {
-                             if let Some(ansi) = hex_to_ansi(s) {
-                                 final_output.push_str(&ansi);
-                             }
-                        },
-                        _ => {
-                            // Unknown tag
-                        }
+                    tokens.push(Token::Tag(chars[i + 1..i + 1 + len].iter().collect()));
+                    i += len + 2;
+                }
+                None => {
+                    text.push('!');
+                    i += 1;
+                }
+            },
+            '%' => {
+                let name_len = chars[i + 1..]
+                    .iter()
+                    .take_while(|n| n.is_ascii_alphanumeric() || **n == '_')
+                    .count();
+                if name_len > 0 && chars.get(i + 1 + name_len) == Some(&'%') {
+                    if !text.is_empty() {
+                        tokens.push(Token::Text(std::mem::take(&mut text)));
                     }
+                    tokens.push(Token::Var(chars[i + 1..i + 1 + name_len].iter().collect()));
+                    i += name_len + 2;
+                } else {
+                    text.push('%');
+                    i += 1;
                 }
+            }
+            _ => {
+                text.push(c);
+                i += 1;
+            }
+        }
+    }
+    if !text.is_empty() {
+        tokens.push(Token::Text(text));
+    }
+    tokens
+}
+
+// Control characters in a substituted value (a directory called `a\x1B[2Jb`,
+// a branch name with a newline) would move the cursor or restyle the
+// terminal behind Reedline's back and break its width math. Show them as `?`.
+fn sanitize(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| if c.is_control() { '?' } else { c })
+        .collect()
+}
+
+/// Variables `variable_value` knows besides the context and slow segments.
+pub const VARIABLES: &[&str] = &[
+    "username", "hostname", "user", "root_indicator", "shlvl", "directory", "time", "duration",
+];
+
+fn variable_value(name: &str, config: &RootConfig, state: &PromptState) -> Option<String> {
+    let value = match name {
+        "username" => config.config.username.clone(),
+        "hostname" => hostname(),
+        // Real login user, unlike the configurable %username%
+        "user" => login_user(),
+        "root_indicator" => if is_root() { "#" } else { "$" }.to_string(),
+        "shlvl" => env::var("SHLVL").unwrap_or_else(|_| "1".to_string()),
+        "directory" => {
+            let cwd = env::current_dir().unwrap_or_default();
+            let cwd_str = cwd.to_string_lossy();
+            // Replace home with ~
+            let home = env::var("HOME").unwrap_or_default();
+            if !home.is_empty() && cwd_str.starts_with(&home) {
+                cwd_str.replacen(&home, "~", 1)
             } else {
-                // No closing '!', treat as literal
-                final_output.push('!');
-                final_output.push_str(&tag_content);
+                cwd_str.to_string()
             }
-        } else {
-            final_output.push(c);
+        }
+        "time" => Local::now().format("%H:%M:%S").to_string(),
+        // Empty unless the last command passed the threshold
+        "duration" => {
+            let threshold = Duration::from_millis(config.config.duration_threshold_ms);
+            match state.last_duration {
+                Some(d) if d >= threshold => format_duration(d),
+                _ => String::new(),
+            }
+        }
+        // Language/environment context (%rust_toolchain%, %ssh%, ...),
+        // computed once per prompt by `SegmentCache::refresh`
+        n if CONTEXT_SEGMENTS.contains(&n) => state.segments.get(n).cloned().unwrap_or_default(),
+        // Slow segments (%git_branch%, ...) computed in the background
+        n => state.segments.get(n)?.clone(),
+    };
+    Some(value)
+}
+
+pub fn format_prompt(template: &str, config: &RootConfig, state: &PromptState) -> String {
+    let mut output = String::new();
+
+    for token in tokenize(template) {
+        match token {
+            Token::Text(text) => output.push_str(&text),
+            Token::Var(name) => match variable_value(&name, config, state) {
+                Some(value) => output.push_str(&sanitize(&value)),
+                // Unknown variable, keep it visible so typos are easy to spot
+                None => {
+                    output.push('%');
+                    output.push_str(&name);
+                    output.push('%');
+                }
+            },
+            // Tag content is a color spec (e.g. "bold,yellow", "lightpink")
+            Token::Tag(tag) => output.push_str(&theme::resolve(&tag, config).prefix()),
         }
     }
 
-    final_output
+    output
 }
 
 #[cfg(test)]
@@ -118,12 +210,20 @@ mod tests {
     use crate::config::{RootConfig, ConfigSection, ThemeSection};
     use std::collections::HashMap;
 
-    #[test]
-    fn test_variable_replacement() {
-        let config = RootConfig {
+    fn test_config() -> RootConfig {
+        RootConfig {
+            version: 1,
             config: ConfigSection {
                 username: "testuser".to_string(),
                 editor: "nano".to_string(),
+                duration_threshold_ms: 2000,
+                notify_long_commands: false,
+                segment_timeout_ms: 50,
+                theme: String::new(),
+                export_ls_colors: true,
+                theme_light: String::new(),
+                theme_dark: String::new(),
+                icons: false,
             },
             theme: ThemeSection {
                 prompt_template: "".to_string(),
@@ -135,12 +235,122 @@ mod tests {
                 body: "".to_string(),
                 active: "".to_string(),
                 disable: "".to_string(),
+                git: Default::default(),
                 files: HashMap::new(),
+                icons: HashMap::new(),
             },
             colors: HashMap::new(),
-        };
+        }
+    }
 
-        let res = format_prompt("Hello %username%", &config);
+    #[test]
+    fn test_variable_replacement() {
+        let config = test_config();
+        let res = format_prompt("Hello %username%", &config, &PromptState::default());
         assert_eq!(res, "Hello testuser");
     }
+
+    #[test]
+    fn test_duration_threshold() {
+        let config = test_config();
+        let short = PromptState { last_duration: Some(Duration::from_millis(500)), ..Default::default() };
+        assert_eq!(format_prompt("[%duration%]", &config, &short), "[]");
+
+        let long = PromptState { last_duration: Some(Duration::from_millis(4200)), ..Default::default() };
+        assert_eq!(format_prompt("[%duration%]", &config, &long), "[4.2s]");
+    }
+
+    #[test]
+    fn test_segment_replacement() {
+        let config = test_config();
+        let mut state = PromptState::default();
+        state.segments.insert("git_branch", "main*".to_string());
+        assert_eq!(format_prompt("(%git_branch%)", &config, &state), "(main*)");
+    }
+
+    #[test]
+    fn test_root_indicator() {
+        let config = test_config();
+        let state = PromptState::default();
+        let indicator = format_prompt("%root_indicator%", &config, &state);
+        assert_eq!(indicator, if is_root() { "#" } else { "$" });
+    }
+
+    // Removes SGR sequences the way Reedline does before measuring the prompt.
+    // Panics on anything that isn't a complete `ESC [ ... m`, since a stray
+    // escape would throw off Reedline's cursor column.
+    fn strip_sgr(s: &str) -> String {
+        let mut out = String::new();
+        let mut chars = s.chars();
+        while let Some(c) = chars.next() {
+            if c == '\x1B' {
+                assert_eq!(chars.next(), Some('['), "escape that isn't CSI in {:?}", s);
+                loop {
+                    match chars.next() {
+                        Some('m') => break,
+                        Some(p) if p.is_ascii_digit() || p == ';' => {}
+                        other => panic!("unterminated SGR ({:?}) in {:?}", other, s),
+                    }
+                }
+            } else {
+                out.push(c);
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn test_double_bang_is_literal() {
+        let config = test_config();
+        let state = PromptState::default();
+        assert_eq!(format_prompt("Hi!! there", &config, &state), "Hi! there");
+        assert_eq!(format_prompt("!red!!!!reset!", &config, &state), "\x1B[31m!\x1B[0m");
+    }
+
+    #[test]
+    fn test_unclosed_bang_and_percent_are_text() {
+        let config = test_config();
+        let state = PromptState::default();
+        assert_eq!(format_prompt("wow! such", &config, &state), "wow! such");
+        assert_eq!(format_prompt("100% %nope% %", &config, &state), "100% %nope% %");
+    }
+
+    #[test]
+    fn test_values_are_not_parsed_as_tags() {
+        let mut config = test_config();
+        config.config.username = "Hello!red!World!".to_string();
+        let mut state = PromptState::default();
+        state.segments.insert("git_branch", "feat/!!bang".to_string());
+
+        let res = format_prompt("%username% %git_branch%", &config, &state);
+        assert_eq!(res, "Hello!red!World! feat/!!bang");
+    }
+
+    #[test]
+    fn test_control_characters_are_escaped() {
+        let mut config = test_config();
+        config.config.username = "evil\x1B[2J\nname".to_string();
+        let res = format_prompt("%username%", &config, &PromptState::default());
+        assert_eq!(res, "evil?[2J?name");
+    }
+
+    #[test]
+    fn test_visible_width_matches_text() {
+        let mut config = test_config();
+        config.colors.insert("pink".to_string(), "#FFC0CB".to_string());
+        config.config.username = "dev!".to_string();
+        let res = format_prompt(
+            "!bold,pink!%username%!reset!@!#00ff00!box!unknown! ✓ !!",
+            &config,
+            &PromptState::default(),
+        );
+        assert_eq!(strip_sgr(&res), "dev!@box ✓ !");
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(Duration::from_millis(850)), "850ms");
+        assert_eq!(format_duration(Duration::from_secs(187)), "3m 07s");
+        assert_eq!(format_duration(Duration::from_secs(3720)), "1h 02m");
+    }
 }
@@ -0,0 +1,280 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::context::{context_segment, CONTEXT_SEGMENTS};
+
+/// Prompt variables that are too slow to compute before every `read_line`.
+/// They run on background threads and are cached per directory.
+pub const SLOW_SEGMENTS: &[&str] = &["git_branch"];
+
+/// Shown in place of a slow segment that has no cached value yet.
+pub const PLACEHOLDER: &str = "…";
+
+/// Directories whose values are kept, most recently visited first.
+const CACHED_DIRS: usize = 16;
+
+type Compute = Arc<dyn Fn(&str, &Path) -> String + Send + Sync>;
+
+#[derive(Default)]
+struct Inner {
+    values: HashMap<(&'static str, PathBuf), String>,
+    in_flight: HashSet<(&'static str, PathBuf)>,
+    recent: VecDeque<PathBuf>,
+    /// Context segments used by the current prompt, computed once per
+    /// `refresh` rather than on every repaint.
+    context: HashMap<&'static str, String>,
+}
+
+#[derive(Clone)]
+pub struct SegmentCache {
+    inner: Arc<(Mutex<Inner>, Condvar)>,
+    compute: Compute,
+}
+
+impl SegmentCache {
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::default(),
+            compute: Arc::new(compute_segment),
+        }
+    }
+
+    /// Starts computing every slow segment used by `template` for `dir` and
+    /// waits at most `timeout` for them. Anything still running after that
+    /// keeps going in the background; until it's done the prompt shows the
+    /// old value or `PLACEHOLDER`, and picks up the result the next time
+    /// Reedline redraws it (the next keystroke or prompt).
+    pub fn refresh(&self, template: &str, dir: &Path, timeout: Duration) {
+        let context = CONTEXT_SEGMENTS
+            .iter()
+            .filter(|name| template.contains(&format!("%{}%", name)))
+            .map(|&name| (name, context_segment(name, dir)))
+            .collect();
+
+        let (lock, cvar) = &*self.inner;
+        let mut pending = Vec::new();
+        {
+            let mut inner = lock.lock().unwrap();
+            inner.context = context;
+            inner.visit(dir);
+            for &name in SLOW_SEGMENTS {
+                if !template.contains(&format!("%{}%", name)) {
+                    continue;
+                }
+                let key = (name, dir.to_path_buf());
+                pending.push(key.clone());
+                if inner.in_flight.insert(key.clone()) {
+                    let cache = self.clone();
+                    thread::spawn(move || {
+                        let value = (cache.compute)(key.0, &key.1);
+                        cache.finish(key, value);
+                    });
+                }
+            }
+        }
+
+        let deadline = Instant::now() + timeout;
+        let mut inner = lock.lock().unwrap();
+        while pending.iter().any(|k| inner.in_flight.contains(k)) {
+            let now = Instant::now();
+            if now >= deadline {
+                break;
+            }
+            inner = cvar.wait_timeout(inner, deadline - now).unwrap().0;
+        }
+    }
+
+    /// Current values for `dir`, with placeholders for anything not yet
+    /// known, plus the context segments from the last `refresh`.
+    pub fn snapshot(&self, dir: &Path) -> HashMap<&'static str, String> {
+        let inner = self.inner.0.lock().unwrap();
+        let mut values: HashMap<&'static str, String> = SLOW_SEGMENTS
+            .iter()
+            .map(|&name| {
+                let value = inner
+                    .values
+                    .get(&(name, dir.to_path_buf()))
+                    .cloned()
+                    .unwrap_or_else(|| PLACEHOLDER.to_string());
+                (name, value)
+            })
+            .collect();
+        values.extend(inner.context.iter().map(|(&name, value)| (name, value.clone())));
+        values
+    }
+
+    fn finish(&self, key: (&'static str, PathBuf), value: String) {
+        let (lock, cvar) = &*self.inner;
+        let mut inner = lock.lock().unwrap();
+        inner.in_flight.remove(&key);
+        // Results for a directory that has since been evicted are dropped.
+        if inner.recent.contains(&key.1) {
+            inner.values.insert(key, value);
+        }
+        drop(inner);
+        cvar.notify_all();
+    }
+}
+
+impl Inner {
+    /// Moves `dir` to the front of the recent list, evicting the values of
+    /// the least recently visited directory past `CACHED_DIRS`.
+    fn visit(&mut self, dir: &Path) {
+        self.recent.retain(|d| d != dir);
+        self.recent.push_front(dir.to_path_buf());
+        while self.recent.len() > CACHED_DIRS {
+            if let Some(old) = self.recent.pop_back() {
+                self.values.retain(|(_, d), _| *d != old);
+            }
+        }
+    }
+}
+
+fn compute_segment(name: &str, dir: &Path) -> String {
+    match name {
+        "git_branch" => git_branch(dir),
+        _ => String::new(),
+    }
+}
+
+fn git_branch(dir: &Path) -> String {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["status", "--porcelain=v1", "--branch"])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output();
+
+    match output {
+        Ok(out) if out.status.success() => {
+            parse_git_branch(&String::from_utf8_lossy(&out.stdout))
+        }
+        _ => String::new(),
+    }
+}
+
+/// Turns `git status --porcelain --branch` output into `branch` or `branch*`
+/// when the work tree has changes.
+fn parse_git_branch(status: &str) -> String {
+    let mut lines = status.lines();
+    let header = match lines.next().and_then(|l| l.strip_prefix("## ")) {
+        Some(h) => h,
+        None => return String::new(),
+    };
+
+    let branch = if let Some(rest) = header.strip_prefix("No commits yet on ") {
+        rest
+    } else if header.starts_with("HEAD (no branch)") {
+        "HEAD"
+    } else {
+        header.split("...").next().unwrap_or(header)
+    };
+    let branch = branch.split_whitespace().next().unwrap_or("");
+
+    let dirty = lines.any(|l| !l.trim().is_empty());
+    if dirty {
+        format!("{}*", branch)
+    } else {
+        branch.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A cache whose segments take `delay` and return
+    /// whatever `value` holds when they finish.
+    fn cache(delay: Duration, value: Arc<Mutex<String>>) -> SegmentCache {
+        SegmentCache {
+            inner: Arc::default(),
+            compute: Arc::new(move |_, _| {
+                thread::sleep(delay);
+                value.lock().unwrap().clone()
+            }),
+        }
+    }
+
+    fn wait_idle(cache: &SegmentCache) {
+        let (lock, cvar) = &*cache.inner;
+        let mut inner = lock.lock().unwrap();
+        while !inner.in_flight.is_empty() {
+            inner = cvar.wait(inner).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_refresh_times_out_with_placeholder() {
+        let cache = cache(Duration::from_millis(300), Arc::new(Mutex::new("main".to_string())));
+        let dir = Path::new("/repo");
+        let started = Instant::now();
+        cache.refresh("%git_branch%", dir, Duration::from_millis(20));
+        assert!(started.elapsed() < Duration::from_millis(250));
+        assert_eq!(cache.snapshot(dir)["git_branch"], PLACEHOLDER);
+
+        wait_idle(&cache);
+        assert_eq!(cache.snapshot(dir)["git_branch"], "main");
+    }
+
+    #[test]
+    fn test_stale_value_until_refreshed() {
+        let value = Arc::new(Mutex::new("main".to_string()));
+        let cache = cache(Duration::from_millis(100), value.clone());
+        let dir = Path::new("/repo");
+        cache.refresh("%git_branch%", dir, Duration::from_secs(5));
+        assert_eq!(cache.snapshot(dir)["git_branch"], "main");
+
+        *value.lock().unwrap() = "main*".to_string();
+        cache.refresh("%git_branch%", dir, Duration::ZERO);
+        assert_eq!(cache.snapshot(dir)["git_branch"], "main");
+        wait_idle(&cache);
+        assert_eq!(cache.snapshot(dir)["git_branch"], "main*");
+    }
+
+    #[test]
+    fn test_only_recent_dirs_are_kept() {
+        let cache = cache(Duration::ZERO, Arc::new(Mutex::new("main".to_string())));
+        for i in 0..CACHED_DIRS + 4 {
+            cache.refresh("%git_branch%", &PathBuf::from(format!("/repo{}", i)), Duration::from_secs(5));
+        }
+        wait_idle(&cache);
+        let inner = cache.inner.0.lock().unwrap();
+        assert_eq!(inner.values.len(), CACHED_DIRS);
+        assert!(!inner.values.contains_key(&("git_branch", PathBuf::from("/repo0"))));
+        drop(inner);
+        assert_eq!(cache.snapshot(Path::new("/repo0"))["git_branch"], PLACEHOLDER);
+    }
+
+    #[test]
+    fn test_context_computed_once_per_refresh() {
+        let dir = std::env::temp_dir().join(format!("aeroshell-context-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join(".nvmrc"), "v20.11.0\n").unwrap();
+        let cache = cache(Duration::ZERO, Arc::new(Mutex::new(String::new())));
+
+        cache.refresh("%node_version%", &dir, Duration::ZERO);
+        std::fs::write(dir.join(".nvmrc"), "v22.0.0\n").unwrap();
+        assert_eq!(cache.snapshot(&dir)["node_version"], "v20.11.0");
+        cache.refresh("%node_version%", &dir, Duration::ZERO);
+        assert_eq!(cache.snapshot(&dir)["node_version"], "v22.0.0");
+        cache.refresh("%git_branch%", &dir, Duration::ZERO);
+        assert!(!cache.snapshot(&dir).contains_key("node_version"));
+
+        wait_idle(&cache);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_parse_git_branch() {
+        assert_eq!(parse_git_branch("## main...origin/main [ahead 1]\n"), "main");
+        assert_eq!(parse_git_branch("## dev\n M src/main.rs\n"), "dev*");
+        assert_eq!(parse_git_branch("## No commits yet on trunk\n"), "trunk");
+        assert_eq!(parse_git_branch("## HEAD (no branch)\n"), "HEAD");
+        assert_eq!(parse_git_branch(""), "");
+    }
+}
@@ -0,0 +1,403 @@
+use std::env;
+use std::fs;
+use std::io::IsTerminal;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// How many colors the terminal we're attached to can show.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ColorSupport {
+    None,
+    Ansi16,
+    Ansi256,
+    TrueColor,
+}
+
+/// Works out color support for the current process from `NO_COLOR`,
+/// `CLICOLOR_FORCE`, `COLORTERM`, `TERM` and the terminfo database.
+pub fn detect_color_support() -> ColorSupport {
+    let term = env::var("TERM").ok();
+    let terminfo_colors = term.as_deref().and_then(terminfo_max_colors);
+    color_support_from(
+        |name| env::var(name).ok(),
+        std::io::stdout().is_terminal(),
+        terminfo_colors,
+    )
+}
+
+fn color_support_from(
+    var: impl Fn(&str) -> Option<String>,
+    is_tty: bool,
+    terminfo_colors: Option<i32>,
+) -> ColorSupport {
+    // https://no-color.org: any non-empty value disables color.
+    if var("NO_COLOR").is_some_and(|v| !v.is_empty()) {
+        return ColorSupport::None;
+    }
+    let forced = var("CLICOLOR_FORCE").is_some_and(|v| !v.is_empty() && v != "0");
+    if !is_tty && !forced {
+        return ColorSupport::None;
+    }
+
+    let term = var("TERM").unwrap_or_default();
+    if term == "dumb" {
+        return if forced { ColorSupport::Ansi16 } else { ColorSupport::None };
+    }
+
+    let colorterm = var("COLORTERM").unwrap_or_default();
+    if colorterm == "truecolor" || colorterm == "24bit" || term.ends_with("-direct") {
+        return ColorSupport::TrueColor;
+    }
+
+    match terminfo_colors {
+        Some(n) if n >= 0x1000000 => ColorSupport::TrueColor,
+        Some(n) if n >= 256 => ColorSupport::Ansi256,
+        Some(n) if n >= 8 => ColorSupport::Ansi16,
+        Some(_) if forced => ColorSupport::Ansi16,
+        Some(_) => ColorSupport::None,
+        // No terminfo entry: guess from the name, like most CLI tools do.
+        None if term.contains("256color") => ColorSupport::Ansi256,
+        None => ColorSupport::Ansi16,
+    }
+}
+
+/// Whether the terminal draws on a light or a dark background.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Background {
+    Light,
+    Dark,
+}
+
+static BACKGROUND: Mutex<Option<Background>> = Mutex::new(None);
+
+/// The terminal background, detected on first use and then cached.
+pub fn background() -> Background {
+    let mut cached = BACKGROUND.lock().unwrap();
+    *cached.get_or_insert_with(detect_background)
+}
+
+/// Detects the background again, e.g. after the terminal theme changed.
+pub fn refresh_background() -> Background {
+    let detected = detect_background();
+    *BACKGROUND.lock().unwrap() = Some(detected);
+    detected
+}
+
+// Asks the terminal for its background color (OSC 11), then falls back to
+// the `COLORFGBG` hint some terminals export, then assumes dark.
+fn detect_background() -> Background {
+    if let Some(rgb) = query_background_color(Duration::from_millis(150)) {
+        return background_from_rgb(rgb);
+    }
+    env::var("COLORFGBG")
+        .ok()
+        .and_then(|v| background_from_colorfgbg(&v))
+        .unwrap_or(Background::Dark)
+}
+
+fn background_from_rgb((r, g, b): (u8, u8, u8)) -> Background {
+    // Rec. 709 luma; mid-grey and up counts as light.
+    let luma = 0.2126 * r as f32 + 0.7152 * g as f32 + 0.0722 * b as f32;
+    if luma >= 128.0 { Background::Light } else { Background::Dark }
+}
+
+/// `COLORFGBG` is `fg;bg` (sometimes `fg;default;bg`) with palette indices.
+fn background_from_colorfgbg(value: &str) -> Option<Background> {
+    let bg: u8 = value.rsplit(';').next()?.trim().parse().ok()?;
+    Some(match bg {
+        7 | 9..=15 => Background::Light,
+        _ => Background::Dark,
+    })
+}
+
+/// Parses an OSC 11 reply such as `ESC ] 11 ; rgb:ffff/ffff/dddd BEL`.
+/// Components may have 1-4 hex digits each; only the top byte is kept.
+fn parse_osc11_response(reply: &str) -> Option<(u8, u8, u8)> {
+    let start = reply.find("11;")? + 3;
+    let body = reply[start..]
+        .trim_end_matches(['\x07', '\\'])
+        .trim_end_matches('\x1B');
+    let spec = body.strip_prefix("rgb:").or_else(|| body.strip_prefix("rgba:"))?;
+    let mut parts = spec.split('/').map(|p| {
+        let p = p.trim();
+        // Checked before the shift below, which overflows past 7 digits.
+        if p.is_empty() || p.len() > 4 || !p.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        let v = u32::from_str_radix(p, 16).ok()?;
+        let max = (1u32 << (4 * p.len() as u32)) - 1;
+        Some((v * 255 / max) as u8)
+    });
+    Some((parts.next()??, parts.next()??, parts.next()??))
+}
+
+#[cfg(unix)]
+fn query_background_color(timeout: Duration) -> Option<(u8, u8, u8)> {
+    use std::io::{Read, Write};
+    use std::os::unix::io::{AsRawFd, RawFd};
+    use std::time::Instant;
+
+    /// Puts the tty back in its original mode, however the query returns.
+    struct RestoreMode {
+        fd: RawFd,
+        original: libc::termios,
+    }
+
+    impl Drop for RestoreMode {
+        fn drop(&mut self) {
+            unsafe { libc::tcsetattr(self.fd, libc::TCSANOW, &self.original) };
+        }
+    }
+
+    if !std::io::stdin().is_terminal() || !std::io::stdout().is_terminal() {
+        return None;
+    }
+    let mut tty = fs::OpenOptions::new().read(true).write(true).open("/dev/tty").ok()?;
+    let fd = tty.as_raw_fd();
+
+    // Raw-ish mode so the reply isn't echoed or line buffered.
+    let mut original: libc::termios = unsafe { std::mem::zeroed() };
+    if unsafe { libc::tcgetattr(fd, &mut original) } != 0 {
+        return None;
+    }
+    let mut raw = original;
+    raw.c_lflag &= !(libc::ICANON | libc::ECHO);
+    raw.c_cc[libc::VMIN] = 0;
+    raw.c_cc[libc::VTIME] = 0;
+    unsafe { libc::tcsetattr(fd, libc::TCSANOW, &raw) };
+    let restore = RestoreMode { fd, original };
+
+    let mut reply = Vec::new();
+    if tty.write_all(b"\x1B]11;?\x07").and_then(|_| tty.flush()).is_ok() {
+        let deadline = Instant::now() + timeout;
+        let mut buf = [0u8; 64];
+        while Instant::now() < deadline {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let mut pfd = libc::pollfd { fd, events: libc::POLLIN, revents: 0 };
+            if unsafe { libc::poll(&mut pfd, 1, remaining.as_millis() as i32) } <= 0 {
+                break;
+            }
+            match tty.read(&mut buf) {
+                Ok(n) if n > 0 => reply.extend_from_slice(&buf[..n]),
+                _ => break,
+            }
+            if reply.ends_with(b"\x07") || reply.ends_with(b"\x1B\\") {
+                break;
+            }
+        }
+    }
+
+    drop(restore);
+    parse_osc11_response(&String::from_utf8_lossy(&reply))
+}
+
+#[cfg(not(unix))]
+fn query_background_color(_timeout: Duration) -> Option<(u8, u8, u8)> {
+    None
+}
+
+fn terminfo_dirs() -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    if let Ok(dir) = env::var("TERMINFO") {
+        dirs.push(PathBuf::from(dir));
+    }
+    if let Ok(home) = env::var("HOME") {
+        dirs.push(PathBuf::from(home).join(".terminfo"));
+    }
+    if let Ok(list) = env::var("TERMINFO_DIRS") {
+        dirs.extend(list.split(':').filter(|d| !d.is_empty()).map(PathBuf::from));
+    }
+    for dir in ["/etc/terminfo", "/lib/terminfo", "/usr/share/terminfo", "/usr/lib/terminfo"] {
+        dirs.push(PathBuf::from(dir));
+    }
+    dirs
+}
+
+/// `max_colors` from the compiled terminfo entry for `term`, if there is one.
+fn terminfo_max_colors(term: &str) -> Option<i32> {
+    let first = term.chars().next()?;
+    for dir in terminfo_dirs() {
+        // Linux uses `x/xterm`, macOS uses the hex code of the letter: `78/xterm`.
+        for sub in [first.to_string(), format!("{:x}", first as u32)] {
+            if let Ok(bytes) = fs::read(dir.join(sub).join(term)) {
+                return parse_terminfo_max_colors(&bytes);
+            }
+        }
+    }
+    None
+}
+
+// Reads the `max_colors` number (index 13) out of a compiled terminfo file.
+// See term(5): a 12-byte header, the names, the booleans, padding to an even
+// offset, then the numbers (16-bit, or 32-bit in the extended format).
+fn parse_terminfo_max_colors(bytes: &[u8]) -> Option<i32> {
+    const MAX_COLORS: usize = 13;
+    let word = |i: usize| -> Option<i16> {
+        Some(i16::from_le_bytes([*bytes.get(i)?, *bytes.get(i.checked_add(1)?)?]))
+    };
+    // Sizes and counts; a corrupt file can make them negative.
+    let count = |i: usize| usize::try_from(word(i)?).ok();
+
+    let number_size = match word(0)? {
+        0o432 => 2,
+        0o1036 => 4,
+        _ => return None,
+    };
+    let names_size = count(2)?;
+    let bools_count = count(4)?;
+    let nums_count = count(6)?;
+    if MAX_COLORS >= nums_count {
+        return None;
+    }
+
+    let mut offset = 12usize.checked_add(names_size)?.checked_add(bools_count)?;
+    if offset % 2 == 1 {
+        offset = offset.checked_add(1)?;
+    }
+    let at = offset.checked_add(MAX_COLORS * number_size)?;
+    let value = if number_size == 2 {
+        word(at)? as i32
+    } else {
+        i32::from_le_bytes(bytes.get(at..at.checked_add(4)?)?.try_into().ok()?)
+    };
+    (value >= 0).then_some(value)
+}
+
+/// Width of the terminal stdout is attached to, or `None` when stdout is
+/// piped or redirected. `$COLUMNS` is the fallback if the ioctl fails.
+pub fn stdout_width() -> Option<usize> {
+    if !std::io::stdout().is_terminal() {
+        return None;
+    }
+    #[cfg(unix)]
+    {
+        let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+        let rc = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) };
+        if rc == 0 && size.ws_col > 0 {
+            return Some(size.ws_col as usize);
+        }
+    }
+    Some(env::var("COLUMNS").ok().and_then(|c| c.parse().ok()).unwrap_or(80))
+}
+
+/// Columns `text` occupies on screen: escape sequences take none, wide
+/// (CJK, emoji) characters take two.
+pub fn display_width(text: &str) -> usize {
+    use unicode_width::UnicodeWidthChar;
+
+    let mut width = 0;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\x1B' {
+            match chars.next() {
+                // CSI: parameters up to a final byte in @..~
+                Some('[') => {
+                    for p in chars.by_ref() {
+                        if ('@'..='~').contains(&p) {
+                            break;
+                        }
+                    }
+                }
+                // OSC (e.g. hyperlinks): up to BEL or ST
+                Some(']') => {
+                    while let Some(p) = chars.next() {
+                        if p == '\x07' || (p == '\x1B' && chars.next_if_eq(&'\\').is_some()) {
+                            break;
+                        }
+                    }
+                }
+                _ => {}
+            }
+            continue;
+        }
+        width += c.width().unwrap_or(0);
+    }
+    width
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn support(vars: &[(&str, &str)], tty: bool, terminfo: Option<i32>) -> ColorSupport {
+        let vars: HashMap<String, String> =
+            vars.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        color_support_from(|name| vars.get(name).cloned(), tty, terminfo)
+    }
+
+    #[test]
+    fn test_env_detection() {
+        assert_eq!(support(&[("COLORTERM", "truecolor"), ("TERM", "xterm-256color")], true, Some(256)), ColorSupport::TrueColor);
+        assert_eq!(support(&[("TERM", "xterm-256color")], true, Some(256)), ColorSupport::Ansi256);
+        assert_eq!(support(&[("TERM", "linux")], true, Some(8)), ColorSupport::Ansi16);
+        assert_eq!(support(&[("TERM", "tmux-256color")], true, None), ColorSupport::Ansi256);
+        assert_eq!(support(&[("TERM", "dumb")], true, None), ColorSupport::None);
+        assert_eq!(support(&[("NO_COLOR", "1"), ("COLORTERM", "truecolor")], true, None), ColorSupport::None);
+        assert_eq!(support(&[("TERM", "xterm-256color")], false, Some(256)), ColorSupport::None);
+        assert_eq!(support(&[("TERM", "xterm-256color"), ("CLICOLOR_FORCE", "1")], false, Some(256)), ColorSupport::Ansi256);
+    }
+
+    fn terminfo_fixture(magic: i16, max_colors: i32) -> Vec<u8> {
+        let names = b"test|fixture\0";
+        let bools = [1u8; 3];
+        let mut out = Vec::new();
+        for v in [magic, names.len() as i16, bools.len() as i16, 15, 0, 0] {
+            out.extend_from_slice(&v.to_le_bytes());
+        }
+        out.extend_from_slice(names);
+        out.extend_from_slice(&bools);
+        if out.len() % 2 == 1 {
+            out.push(0);
+        }
+        for i in 0..15 {
+            let v = if i == 13 { max_colors } else { -1 };
+            if magic == 0o432 {
+                out.extend_from_slice(&(v as i16).to_le_bytes());
+            } else {
+                out.extend_from_slice(&v.to_le_bytes());
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn test_background_hints() {
+        assert_eq!(parse_osc11_response("\x1B]11;rgb:ffff/ffff/dddd\x07"), Some((255, 255, 221)));
+        assert_eq!(parse_osc11_response("\x1B]11;rgb:28/2a/36\x1B\\"), Some((40, 42, 54)));
+        assert_eq!(parse_osc11_response("garbage"), None);
+        assert_eq!(parse_osc11_response("\x1B]11;rgb:ffffffff/0/0\x07"), None);
+        assert_eq!(parse_osc11_response("\x1B]11;rgb:fffff/0/0\x07"), None);
+        assert_eq!(parse_osc11_response("\x1B]11;rgb:+f/0/0\x07"), None);
+
+        assert_eq!(background_from_rgb((253, 246, 227)), Background::Light);
+        assert_eq!(background_from_rgb((40, 42, 54)), Background::Dark);
+
+        assert_eq!(background_from_colorfgbg("0;15"), Some(Background::Light));
+        assert_eq!(background_from_colorfgbg("15;default;0"), Some(Background::Dark));
+        assert_eq!(background_from_colorfgbg("nonsense"), None);
+    }
+
+    #[test]
+    fn test_parse_terminfo() {
+        assert_eq!(parse_terminfo_max_colors(&terminfo_fixture(0o432, 8)), Some(8));
+        assert_eq!(parse_terminfo_max_colors(&terminfo_fixture(0o1036, 0x1000000)), Some(0x1000000));
+        assert_eq!(parse_terminfo_max_colors(b"garbage"), None);
+
+        // Negative sizes in a corrupt header are rejected, not wrapped.
+        for field in [2, 4, 6] {
+            let mut corrupt = terminfo_fixture(0o432, 8);
+            corrupt[field..field + 2].copy_from_slice(&(-2i16).to_le_bytes());
+            assert_eq!(parse_terminfo_max_colors(&corrupt), None);
+        }
+    }
+
+    #[test]
+    fn test_display_width() {
+        assert_eq!(display_width("main.rs"), 7);
+        assert_eq!(display_width("\x1B[1;38;2;255;165;0mmain.rs\x1B[0m"), 7);
+        assert_eq!(display_width("日本語.txt"), 10);
+        assert_eq!(display_width("\x1B]8;;file:///x\x1B\\x\x1B]8;;\x07"), 1);
+    }
+}
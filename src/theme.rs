@@ -0,0 +1,562 @@
+use crate::config::{get_themes_dir, RootConfig, ThemeSection};
+use crate::terminal::ColorSupport;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+pub const RESET: &str = "\x1B[0m";
+
+// Detected once at startup; stays TrueColor (no downgrading) in tests.
+static COLOR_SUPPORT: AtomicU8 = AtomicU8::new(ColorSupport::TrueColor as u8);
+
+pub fn set_color_support(support: ColorSupport) {
+    COLOR_SUPPORT.store(support as u8, Ordering::Relaxed);
+}
+
+pub fn color_support() -> ColorSupport {
+    match COLOR_SUPPORT.load(Ordering::Relaxed) {
+        0 => ColorSupport::None,
+        1 => ColorSupport::Ansi16,
+        2 => ColorSupport::Ansi256,
+        _ => ColorSupport::TrueColor,
+    }
+}
+
+/// The reset sequence, or "" when colors are disabled.
+pub fn reset() -> &'static str {
+    if color_support() == ColorSupport::None { "" } else { RESET }
+}
+
+// xterm's default values for the 16 standard colors.
+pub const ANSI16_RGB: [(u8, u8, u8); 16] = [
+    (0, 0, 0), (205, 0, 0), (0, 205, 0), (205, 205, 0),
+    (0, 0, 238), (205, 0, 205), (0, 205, 205), (229, 229, 229),
+    (127, 127, 127), (255, 0, 0), (0, 255, 0), (255, 255, 0),
+    (92, 92, 255), (255, 0, 255), (0, 255, 255), (255, 255, 255),
+];
+
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    let d = |x: u8, y: u8| (x as i32 - y as i32).pow(2) as u32;
+    d(a.0, b.0) + d(a.1, b.1) + d(a.2, b.2)
+}
+
+fn fixed_to_rgb(n: u8) -> (u8, u8, u8) {
+    match n {
+        0..=15 => ANSI16_RGB[n as usize],
+        16..=231 => {
+            let i = n - 16;
+            (CUBE_LEVELS[(i / 36) as usize], CUBE_LEVELS[((i / 6) % 6) as usize], CUBE_LEVELS[(i % 6) as usize])
+        }
+        _ => {
+            let v = 8 + 10 * (n - 232);
+            (v, v, v)
+        }
+    }
+}
+
+// Nearest entry in the 6x6x6 cube or the grey ramp (16-255). The first 16
+// are skipped because their actual values depend on the user's palette.
+fn rgb_to_fixed(rgb: (u8, u8, u8)) -> u8 {
+    let level = |v: u8| {
+        CUBE_LEVELS
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, l)| (**l as i32 - v as i32).abs())
+            .map(|(i, _)| i as u8)
+            .unwrap_or(0)
+    };
+    let cube = 16 + 36 * level(rgb.0) + 6 * level(rgb.1) + level(rgb.2);
+
+    let avg = (rgb.0 as u32 + rgb.1 as u32 + rgb.2 as u32) / 3;
+    let grey = 232 + ((avg.saturating_sub(8) + 5) / 10).min(23) as u8;
+
+    if distance(rgb, fixed_to_rgb(grey)) < distance(rgb, fixed_to_rgb(cube)) {
+        grey
+    } else {
+        cube
+    }
+}
+
+fn rgb_to_ansi16(rgb: (u8, u8, u8)) -> u8 {
+    (0..16u8)
+        .min_by_key(|&i| distance(rgb, ANSI16_RGB[i as usize]))
+        .unwrap_or(7)
+}
+
+/// A terminal color as written in the config.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Color {
+    /// One of the 16 standard colors (0-7 normal, 8-15 bright).
+    Ansi(u8),
+    /// An entry of the 256-color palette.
+    Fixed(u8),
+    Rgb(u8, u8, u8),
+}
+
+impl Color {
+    /// Approximate 24-bit value, using xterm's defaults for palette colors.
+    pub fn to_rgb(self) -> (u8, u8, u8) {
+        match self {
+            Color::Ansi(n) => ANSI16_RGB[(n & 15) as usize],
+            Color::Fixed(n) => fixed_to_rgb(n),
+            Color::Rgb(r, g, b) => (r, g, b),
+        }
+    }
+
+    /// The closest color the terminal can show, or `None` without color.
+    pub fn downgrade(self, support: ColorSupport) -> Option<Color> {
+        match (support, self) {
+            (ColorSupport::None, _) => None,
+            (ColorSupport::TrueColor, c) => Some(c),
+            (ColorSupport::Ansi256, Color::Rgb(r, g, b)) => Some(Color::Fixed(rgb_to_fixed((r, g, b)))),
+            (ColorSupport::Ansi256, c) => Some(c),
+            (ColorSupport::Ansi16, Color::Rgb(r, g, b)) => Some(Color::Ansi(rgb_to_ansi16((r, g, b)))),
+            (ColorSupport::Ansi16, Color::Fixed(n)) if n < 16 => Some(Color::Ansi(n)),
+            (ColorSupport::Ansi16, Color::Fixed(n)) => Some(Color::Ansi(rgb_to_ansi16(fixed_to_rgb(n)))),
+            (ColorSupport::Ansi16, c) => Some(c),
+        }
+    }
+
+    fn sgr(&self, background: bool) -> String {
+        match *self {
+            Color::Ansi(n) if n < 8 => format!("{}", if background { 40 } else { 30 } + n as u32),
+            Color::Ansi(n) => format!("{}", if background { 100 } else { 90 } + (n as u32 - 8)),
+            Color::Fixed(n) => format!("{};5;{}", if background { 48 } else { 38 }, n),
+            Color::Rgb(r, g, b) => format!("{};2;{};{};{}", if background { 48 } else { 38 }, r, g, b),
+        }
+    }
+
+    fn to_nu(self) -> nu_ansi_term::Color {
+        use nu_ansi_term::Color as Nu;
+        match self {
+            Color::Ansi(0) => Nu::Black,
+            Color::Ansi(1) => Nu::Red,
+            Color::Ansi(2) => Nu::Green,
+            Color::Ansi(3) => Nu::Yellow,
+            Color::Ansi(4) => Nu::Blue,
+            Color::Ansi(5) => Nu::Purple,
+            Color::Ansi(6) => Nu::Cyan,
+            Color::Ansi(7) => Nu::LightGray,
+            Color::Ansi(8) => Nu::DarkGray,
+            Color::Ansi(9) => Nu::LightRed,
+            Color::Ansi(10) => Nu::LightGreen,
+            Color::Ansi(11) => Nu::LightYellow,
+            Color::Ansi(12) => Nu::LightBlue,
+            Color::Ansi(13) => Nu::LightPurple,
+            Color::Ansi(14) => Nu::LightCyan,
+            Color::Ansi(_) => Nu::White,
+            Color::Fixed(n) => Nu::Fixed(n),
+            Color::Rgb(r, g, b) => Nu::Rgb(r, g, b),
+        }
+    }
+}
+
+/// A resolved color spec, shared by the prompt, `ls`, `proc`, the menus and
+/// Reedline so a name always means the same thing everywhere.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Style {
+    pub fg: Option<Color>,
+    pub bg: Option<Color>,
+    pub bold: bool,
+    pub dim: bool,
+    pub italic: bool,
+    pub underline: bool,
+    /// Clear earlier attributes first (`!reset!` in the prompt).
+    pub reset: bool,
+}
+
+impl Style {
+    /// The SGR escape that switches this style on, or "" for a plain style.
+    /// Colors are downgraded to what the terminal supports.
+    pub fn prefix(&self) -> String {
+        self.prefix_for(color_support())
+    }
+
+    pub fn prefix_for(&self, support: ColorSupport) -> String {
+        let params = self.sgr_params(support);
+        if params.is_empty() {
+            String::new()
+        } else {
+            format!("\x1B[{}m", params)
+        }
+    }
+
+    /// Just the SGR parameters (`1;38;5;214`), as used by `LS_COLORS`.
+    pub fn sgr_params(&self, support: ColorSupport) -> String {
+        if support == ColorSupport::None {
+            return String::new();
+        }
+        let mut codes: Vec<String> = Vec::new();
+        if self.reset {
+            codes.push("0".to_string());
+        }
+        if self.bold {
+            codes.push("1".to_string());
+        }
+        if self.dim {
+            codes.push("2".to_string());
+        }
+        if self.italic {
+            codes.push("3".to_string());
+        }
+        if self.underline {
+            codes.push("4".to_string());
+        }
+        if let Some(fg) = self.fg.and_then(|c| c.downgrade(support)) {
+            codes.push(fg.sgr(false));
+        }
+        if let Some(bg) = self.bg.and_then(|c| c.downgrade(support)) {
+            codes.push(bg.sgr(true));
+        }
+        codes.join(";")
+    }
+
+    /// `text` wrapped in this style and a reset.
+    pub fn paint(&self, text: &str) -> String {
+        let prefix = self.prefix();
+        if prefix.is_empty() {
+            text.to_string()
+        } else {
+            format!("{}{}{}", prefix, text, RESET)
+        }
+    }
+
+    pub fn to_nu(self) -> nu_ansi_term::Style {
+        let support = color_support();
+        let mut style = nu_ansi_term::Style::new();
+        if support == ColorSupport::None {
+            return style;
+        }
+        style.foreground = self.fg.and_then(|c| c.downgrade(support)).map(Color::to_nu);
+        style.background = self.bg.and_then(|c| c.downgrade(support)).map(Color::to_nu);
+        style.is_bold = self.bold;
+        style.is_dimmed = self.dim;
+        style.is_italic = self.italic;
+        style.is_underline = self.underline;
+        style
+    }
+}
+
+/// Color names that are always available, and their ANSI numbers.
+pub const BUILTIN_COLORS: &[(&str, u8)] = &[
+    ("black", 0),
+    ("red", 1),
+    ("green", 2),
+    ("yellow", 3),
+    ("blue", 4),
+    ("magenta", 5),
+    ("purple", 5),
+    ("cyan", 6),
+    ("white", 7),
+    ("grey", 8),
+    ("gray", 8),
+    ("bright_black", 8),
+    ("bright_red", 9),
+    ("bright_green", 10),
+    ("bright_yellow", 11),
+    ("bright_blue", 12),
+    ("bright_magenta", 13),
+    ("bright_cyan", 14),
+    ("bright_white", 15),
+];
+
+/// Words in a color spec that set attributes rather than colors.
+pub const STYLE_WORDS: &[&str] = &["reset", "bold", "dim", "italic", "underline"];
+
+/// Parses a single color: a built-in name, `#RRGGBB`, `#RGB`,
+/// `rgb(r, g, b)` or a 256-color index (`0`-`255`).
+pub fn parse_color(spec: &str) -> Option<Color> {
+    let spec = spec.trim();
+    if let Some((_, n)) = BUILTIN_COLORS.iter().find(|(name, _)| *name == spec) {
+        return Some(Color::Ansi(*n));
+    }
+
+    if let Some(hex) = spec.strip_prefix('#') {
+        // Checked first so the byte slicing below can't split a character.
+        if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        let expanded: String = match hex.len() {
+            3 => hex.chars().flat_map(|c| [c, c]).collect(),
+            6 => hex.to_string(),
+            _ => return None,
+        };
+        let r = u8::from_str_radix(&expanded[0..2], 16).ok()?;
+        let g = u8::from_str_radix(&expanded[2..4], 16).ok()?;
+        let b = u8::from_str_radix(&expanded[4..6], 16).ok()?;
+        return Some(Color::Rgb(r, g, b));
+    }
+
+    if let Some(inner) = spec.strip_prefix("rgb(").and_then(|s| s.strip_suffix(')')) {
+        let parts: Vec<u8> = inner
+            .split(',')
+            .map(|p| p.trim().parse::<u8>())
+            .collect::<Result<_, _>>()
+            .ok()?;
+        if let [r, g, b] = parts[..] {
+            return Some(Color::Rgb(r, g, b));
+        }
+        return None;
+    }
+
+    spec.parse::<u8>().ok().map(Color::Fixed)
+}
+
+/// Resolves a color spec such as `pink`, `bold,#FF8800`, `fg:teal bg:234` or
+/// `dim italic grey` into a [`Style`]. Names from `[colors]` are looked up
+/// first and may themselves be any spec. Unknown words are ignored.
+pub fn resolve(spec: &str, config: &RootConfig) -> Style {
+    let mut style = Style::default();
+    apply(spec, config, &mut style, 0);
+    style
+}
+
+fn apply(spec: &str, config: &RootConfig, style: &mut Style, depth: u8) {
+    // rgb(1, 2, 3) contains commas and spaces, so split around it by hand.
+    for part in split_spec(spec) {
+        let (target_bg, word) = if let Some(rest) = part.strip_prefix("bg:").or_else(|| part.strip_prefix("on:")) {
+            (true, rest)
+        } else {
+            (false, part.strip_prefix("fg:").unwrap_or(part))
+        };
+
+        match word {
+            "reset" => {
+                *style = Style { reset: true, ..Style::default() };
+                continue;
+            }
+            "bold" => { style.bold = true; continue; }
+            "dim" => { style.dim = true; continue; }
+            "italic" => { style.italic = true; continue; }
+            "underline" => { style.underline = true; continue; }
+            _ => {}
+        }
+
+        // Custom colors may be full specs themselves; guard against cycles.
+        if let Some(custom) = config.colors.get(word) {
+            if depth < 8 {
+                let mut inner = Style::default();
+                apply(custom, config, &mut inner, depth + 1);
+                if target_bg {
+                    style.bg = inner.fg.or(inner.bg);
+                } else {
+                    style.fg = inner.fg.or(style.fg);
+                    style.bg = inner.bg.or(style.bg);
+                    style.bold |= inner.bold;
+                    style.dim |= inner.dim;
+                    style.italic |= inner.italic;
+                    style.underline |= inner.underline;
+                }
+                continue;
+            }
+        }
+
+        if let Some(color) = parse_color(word) {
+            if target_bg {
+                style.bg = Some(color);
+            } else {
+                style.fg = Some(color);
+            }
+        }
+    }
+}
+
+/// The words of a color spec, keeping `rgb(r, g, b)` in one piece.
+pub fn split_spec(spec: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut depth = 0;
+    for (i, c) in spec.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' | ' ' | '\t' if depth == 0 => {
+                if start < i {
+                    parts.push(spec[start..i].trim());
+                }
+                start = i + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    if start < spec.len() {
+        parts.push(spec[start..].trim());
+    }
+    parts.into_iter().filter(|p| !p.is_empty()).collect()
+}
+
+/// A standalone theme: the `[theme]` and `[colors]` sections of a config.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ThemeFile {
+    pub theme: ThemeSection,
+    #[serde(default)]
+    pub colors: HashMap<String, String>,
+}
+
+pub const BUILTIN_THEMES: &[(&str, &str)] = &[
+    ("aero", include_str!("../themes/aero.toml")),
+    ("nord", include_str!("../themes/nord.toml")),
+    ("dracula", include_str!("../themes/dracula.toml")),
+    ("solarized-light", include_str!("../themes/solarized-light.toml")),
+    ("mono", include_str!("../themes/mono.toml")),
+];
+
+pub enum ThemeSource {
+    Builtin,
+    User(PathBuf),
+}
+
+/// Every theme by name; files in ~/aeroshell/themes shadow built-ins.
+pub fn list_themes() -> Vec<(String, ThemeSource)> {
+    let mut themes: Vec<(String, ThemeSource)> = BUILTIN_THEMES
+        .iter()
+        .map(|(name, _)| (name.to_string(), ThemeSource::Builtin))
+        .collect();
+
+    if let Ok(entries) = fs::read_dir(get_themes_dir()) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("toml") {
+                continue;
+            }
+            let Some(name) = path.file_stem().map(|s| s.to_string_lossy().to_string()) else {
+                continue;
+            };
+            themes.retain(|(n, _)| *n != name);
+            themes.push((name, ThemeSource::User(path)));
+        }
+    }
+
+    themes.sort_by(|a, b| a.0.cmp(&b.0));
+    themes
+}
+
+/// The text of theme `name` and where it comes from; files in
+/// ~/aeroshell/themes shadow built-ins.
+pub fn theme_source(name: &str) -> Result<(String, ThemeSource), String> {
+    let user_path = get_themes_dir().join(format!("{}.toml", name));
+    if user_path.exists() {
+        let content = fs::read_to_string(&user_path)
+            .map_err(|e| format!("{}: {}", user_path.display(), e))?;
+        Ok((content, ThemeSource::User(user_path)))
+    } else if let Some((_, content)) = BUILTIN_THEMES.iter().find(|(n, _)| *n == name) {
+        Ok((content.to_string(), ThemeSource::Builtin))
+    } else {
+        Err(format!("no theme named '{}'", name))
+    }
+}
+
+pub fn load_theme(name: &str) -> Result<ThemeFile, String> {
+    let (content, source) = theme_source(name)?;
+    let origin = match source {
+        ThemeSource::User(path) => path.display().to_string(),
+        ThemeSource::Builtin => format!("built-in theme '{}'", name),
+    };
+    toml::from_str(&content).map_err(|e| format!("{}: {}", origin, e))
+}
+
+/// Replaces the config's `[theme]` with the theme's and adds its colors.
+pub fn apply_theme(config: &mut RootConfig, theme: ThemeFile) {
+    config.theme = theme.theme;
+    config.colors.extend(theme.colors);
+}
+
+/// The theme `[config] theme` stands for, resolving `auto` to
+/// `theme_light`/`theme_dark` from the terminal background.
+pub fn selected_theme_name(config: &RootConfig) -> String {
+    let name = config.config.theme.trim();
+    if name != "auto" {
+        return name.to_string();
+    }
+    match crate::terminal::background() {
+        crate::terminal::Background::Light => config.config.theme_light.trim().to_string(),
+        crate::terminal::Background::Dark => config.config.theme_dark.trim().to_string(),
+    }
+}
+
+/// Applies `[config] theme`, if set. A broken theme leaves the config's own
+/// `[theme]` in place, with a warning.
+pub fn apply_selected_theme(config: &mut RootConfig) {
+    let name = selected_theme_name(config);
+    if name.is_empty() {
+        return;
+    }
+    match load_theme(&name) {
+        Ok(theme) => apply_theme(config, theme),
+        Err(e) => eprintln!("Warning: could not load theme: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config_with(colors: &[(&str, &str)]) -> RootConfig {
+        RootConfig {
+            colors: colors.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_parse_color_forms() {
+        assert_eq!(parse_color("#FF8800"), Some(Color::Rgb(255, 136, 0)));
+        assert_eq!(parse_color("#f80"), Some(Color::Rgb(255, 136, 0)));
+        assert_eq!(parse_color("rgb(1, 2, 3)"), Some(Color::Rgb(1, 2, 3)));
+        assert_eq!(parse_color("208"), Some(Color::Fixed(208)));
+        assert_eq!(parse_color("grey"), Some(Color::Ansi(8)));
+        assert_eq!(parse_color("#12345"), None);
+        assert_eq!(parse_color("#aéaaa"), None);
+        assert_eq!(parse_color("#ééé"), None);
+        assert_eq!(parse_color("nope"), None);
+    }
+
+    #[test]
+    fn test_resolve_combinations() {
+        let config = config_with(&[("pink", "#FFC0CB"), ("accent", "bold pink")]);
+        let style = resolve("underline,accent bg:rgb(0, 0, 0)", &config);
+        assert_eq!(style.fg, Some(Color::Rgb(255, 192, 203)));
+        assert_eq!(style.bg, Some(Color::Rgb(0, 0, 0)));
+        assert!(style.bold && style.underline);
+        assert_eq!(style.prefix(), "\x1B[1;4;38;2;255;192;203;48;2;0;0;0m");
+    }
+
+    #[test]
+    fn test_grey_is_consistent() {
+        let config = config_with(&[]);
+        let style = resolve("grey", &config);
+        assert_eq!(style.prefix(), "\x1B[90m");
+        assert_eq!(style.to_nu().foreground, Some(nu_ansi_term::Color::DarkGray));
+    }
+
+    #[test]
+    fn test_downgrade() {
+        let orange = resolve("#FFA500", &config_with(&[]));
+        assert_eq!(orange.prefix_for(ColorSupport::TrueColor), "\x1B[38;2;255;165;0m");
+        assert_eq!(orange.prefix_for(ColorSupport::Ansi256), "\x1B[38;5;214m");
+        assert_eq!(orange.prefix_for(ColorSupport::Ansi16), "\x1B[33m");
+        assert_eq!(orange.prefix_for(ColorSupport::None), "");
+
+        assert_eq!(Color::Rgb(128, 128, 128).downgrade(ColorSupport::Ansi256), Some(Color::Fixed(244)));
+        assert_eq!(Color::Fixed(196).downgrade(ColorSupport::Ansi16), Some(Color::Ansi(9)));
+        assert_eq!(Color::Fixed(3).downgrade(ColorSupport::Ansi16), Some(Color::Ansi(3)));
+    }
+
+    #[test]
+    fn test_builtin_themes_parse() {
+        for (name, content) in BUILTIN_THEMES {
+            let theme: Result<ThemeFile, _> = toml::from_str(content);
+            assert!(theme.is_ok(), "built-in theme {} is invalid: {:?}", name, theme.err());
+        }
+    }
+
+    #[test]
+    fn test_custom_color_cycle_terminates() {
+        let config = config_with(&[("a", "b"), ("b", "a")]);
+        assert_eq!(resolve("a", &config), Style::default());
+    }
+}
@@ -0,0 +1,211 @@
+use crate::config::RootConfig;
+use crate::file_class::{is_glob_key, LEGACY_EXTENSIONS};
+use crate::terminal::ColorSupport;
+use crate::theme::{self, ThemeFile, ANSI16_RGB};
+
+/// Formats accepted by `aero theme export --format=...`.
+pub const EXPORT_FORMATS: &[&str] = &["toml", "alacritty", "kitty", "wezterm", "ls_colors"];
+
+const ANSI_NAMES: [&str; 8] = ["black", "red", "green", "yellow", "blue", "magenta", "cyan", "white"];
+
+/// Builds an `LS_COLORS` value from `theme.files`, so GNU `ls`, `fd` and
+/// `eza` color entries the same way the built-in `ls` does.
+pub fn ls_colors(config: &RootConfig, support: ColorSupport) -> String {
+    let mut entries: Vec<String> = Vec::new();
+    let mut keys: Vec<&String> = config.theme.files.keys().collect();
+    keys.sort();
+
+    for key in keys {
+        let params = theme::resolve(&config.theme.files[key], config).sgr_params(support);
+        if params.is_empty() {
+            continue;
+        }
+        let selector = match key.as_str() {
+            "directory" => "di".to_string(),
+            "executable" => "ex".to_string(),
+            "default" => "fi".to_string(),
+            "symlink" => "ln".to_string(),
+            "broken_symlink" => "or".to_string(),
+            "fifo" => "pi".to_string(),
+            "socket" => "so".to_string(),
+            "block_device" => "bd".to_string(),
+            "char_device" => "cd".to_string(),
+            "setuid" => "su".to_string(),
+            "sticky" => "st".to_string(),
+            // LS_COLORS has no notion of dotfiles
+            "hidden" => continue,
+            // GNU ls only understands `*suffix`; other globs would make it
+            // reject the whole variable.
+            k if is_glob_key(k) => match k.strip_prefix('*') {
+                Some(suffix) if !suffix.is_empty() && !is_glob_key(suffix) && !suffix.contains(['=', ':']) => {
+                    k.to_string()
+                }
+                _ => continue,
+            },
+            k => match LEGACY_EXTENSIONS.iter().find(|(name, _)| *name == k) {
+                Some((_, ext)) => format!("*.{}", ext),
+                None => format!("*.{}", k.strip_prefix("file.").unwrap_or(k)),
+            },
+        };
+        entries.push(format!("{}={}", selector, params));
+    }
+    entries.join(":")
+}
+
+fn hex(rgb: (u8, u8, u8)) -> String {
+    format!("#{:02x}{:02x}{:02x}", rgb.0, rgb.1, rgb.2)
+}
+
+/// A terminal palette derived from the theme.
+pub struct Palette {
+    pub foreground: String,
+    pub background: Option<String>,
+    pub cursor: String,
+    /// color0-color15
+    pub ansi: Vec<String>,
+}
+
+// Palette slots come from same-named [colors] entries (`red`, `bright_red`,
+// `purple` for magenta, ...), falling back to xterm's defaults. The
+// background only comes from an explicit `background` color.
+pub fn palette(config: &RootConfig) -> Palette {
+    let lookup = |name: &str| -> Option<String> {
+        config.colors.get(name)?;
+        theme::resolve(name, config).fg.map(|c| hex(c.to_rgb()))
+    };
+
+    let mut ansi = Vec::with_capacity(16);
+    for bright in [false, true] {
+        for (i, name) in ANSI_NAMES.iter().enumerate() {
+            let slot = if bright { i + 8 } else { i };
+            let key = if bright { format!("bright_{}", name) } else { name.to_string() };
+            let value = lookup(&key)
+                .or_else(|| if *name == "magenta" { lookup(if bright { "bright_purple" } else { "purple" }) } else { None })
+                .or_else(|| if bright { lookup(name) } else { None })
+                .unwrap_or_else(|| hex(ANSI16_RGB[slot]));
+            ansi.push(value);
+        }
+    }
+
+    let style_hex = |spec: &str, fallback: (u8, u8, u8)| {
+        hex(theme::resolve(spec, config).fg.map(|c| c.to_rgb()).unwrap_or(fallback))
+    };
+
+    Palette {
+        foreground: style_hex(&config.theme.body, ANSI16_RGB[7]),
+        background: lookup("background"),
+        cursor: style_hex(&config.theme.typing, ANSI16_RGB[7]),
+        ansi,
+    }
+}
+
+/// Renders `config`'s theme in `format` (one of [`EXPORT_FORMATS`]).
+pub fn export(config: &RootConfig, name: &str, format: &str) -> Result<String, String> {
+    let p = palette(config);
+    let out = match format {
+        "toml" => {
+            let file = ThemeFile { theme: config.theme.clone(), colors: config.colors.clone() };
+            toml::to_string_pretty(&file).map_err(|e| e.to_string())?
+        }
+        "alacritty" => {
+            let mut out = format!("# AeroShell theme '{}' for Alacritty\n[colors.primary]\n", name);
+            if let Some(bg) = &p.background {
+                out.push_str(&format!("background = \"{}\"\n", bg));
+            }
+            out.push_str(&format!("foreground = \"{}\"\n\n[colors.cursor]\ncursor = \"{}\"\n", p.foreground, p.cursor));
+            for (section, offset) in [("normal", 0), ("bright", 8)] {
+                out.push_str(&format!("\n[colors.{}]\n", section));
+                for (i, color_name) in ANSI_NAMES.iter().enumerate() {
+                    out.push_str(&format!("{} = \"{}\"\n", color_name, p.ansi[offset + i]));
+                }
+            }
+            out
+        }
+        "kitty" => {
+            let mut out = format!("# AeroShell theme '{}' for kitty\nforeground {}\n", name, p.foreground);
+            if let Some(bg) = &p.background {
+                out.push_str(&format!("background {}\n", bg));
+            }
+            out.push_str(&format!("cursor {}\n", p.cursor));
+            for (i, c) in p.ansi.iter().enumerate() {
+                out.push_str(&format!("color{} {}\n", i, c));
+            }
+            out
+        }
+        "wezterm" => {
+            let list = |colors: &[String]| {
+                colors.iter().map(|c| format!("\"{}\"", c)).collect::<Vec<_>>().join(", ")
+            };
+            let mut out = format!("# AeroShell theme '{}' for WezTerm (~/.config/wezterm/colors/)\n[colors]\n", name);
+            out.push_str(&format!("foreground = \"{}\"\n", p.foreground));
+            if let Some(bg) = &p.background {
+                out.push_str(&format!("background = \"{}\"\n", bg));
+            }
+            out.push_str(&format!("cursor_bg = \"{}\"\n", p.cursor));
+            out.push_str(&format!("ansi = [{}]\n", list(&p.ansi[..8])));
+            out.push_str(&format!("brights = [{}]\n", list(&p.ansi[8..])));
+            out.push_str(&format!("\n[metadata]\nname = \"AeroShell {}\"\n", name));
+            out
+        }
+        "ls_colors" => format!("{}\n", ls_colors(config, ColorSupport::TrueColor)),
+        other => {
+            return Err(format!("unknown format '{}' (expected one of: {})", other, EXPORT_FORMATS.join(", ")))
+        }
+    };
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ls_colors_keys() {
+        let mut config = RootConfig::default();
+        config.theme.files = [
+            ("directory", "bold,blue"),
+            ("executable", "#FFA500"),
+            ("rust", "red"),
+            ("toml", "214"),
+            ("file.zip", "magenta"),
+            ("symlink", "cyan"),
+            ("broken_symlink", "bold,red"),
+            ("hidden", "grey"),
+            ("Cargo.*", "yellow"),
+            ("*.tar.*", "yellow"),
+            ("*.bak", "grey"),
+            ("file.tar.gz", "red"),
+        ]
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        config.colors.clear();
+
+        let value = ls_colors(&config, ColorSupport::TrueColor);
+        assert_eq!(
+            value,
+            "*.bak=90:or=1;31:di=1;34:ex=38;2;255;165;0:*.tar.gz=31:*.zip=35:*.rs=31:ln=36:*.toml=38;5;214"
+        );
+        assert_eq!(ls_colors(&config, ColorSupport::None), "");
+    }
+
+    #[test]
+    fn test_palette_uses_named_colors() {
+        let mut config = RootConfig::default();
+        config.colors.insert("background".to_string(), "#101010".to_string());
+        let p = palette(&config);
+        assert_eq!(p.background.as_deref(), Some("#101010"));
+        assert_eq!(p.ansi[1], "#ff0000"); // red from [colors]
+        assert_eq!(p.ansi[5], "#800080"); // magenta slot filled by purple
+        assert_eq!(p.ansi.len(), 16);
+    }
+
+    #[test]
+    fn test_export_formats_render() {
+        let config = RootConfig::default();
+        for format in EXPORT_FORMATS {
+            assert!(export(&config, "aero", format).is_ok(), "{} failed", format);
+        }
+        assert!(export(&config, "aero", "iterm").is_err());
+    }
+}
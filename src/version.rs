@@ -1,4 +1,4 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 #[derive(Deserialize)]
 struct VersionConfig {
@@ -11,14 +11,34 @@ struct AeroShellVersion {
     stable_version: String,
 }
 
-pub fn get_version_description() -> String {
+/// What `aero about` reports.
+#[derive(Serialize)]
+pub struct BuildInfo {
+    pub version: String,
+    /// "dev" or "stable"
+    pub build_type: &'static str,
+    /// Short commit hash, when built from a git checkout
+    pub git_hash: Option<&'static str>,
+}
+
+pub fn build_info() -> BuildInfo {
     let version_file = include_str!("../version.toml");
     let config: VersionConfig = toml::from_str(version_file).expect("Failed to parse version.toml");
+    let git_hash = Some(env!("AEROSHELL_GIT_HASH")).filter(|hash| !hash.is_empty());
 
     if cfg!(debug_assertions) {
-        format!("v{} (Dev Build)", config.aeroshell.dev_version)
+        BuildInfo { version: config.aeroshell.dev_version, build_type: "dev", git_hash }
+    } else {
+        BuildInfo { version: config.aeroshell.stable_version, build_type: "stable", git_hash }
+    }
+}
+
+pub fn get_version_description() -> String {
+    let info = build_info();
+    if info.build_type == "dev" {
+        format!("v{} (Dev Build)", info.version)
     } else {
-        format!("v{} (Stable)", config.aeroshell.stable_version)
+        format!("v{} (Stable)", info.version)
     }
 }
 